@@ -0,0 +1,25 @@
+mod incremental;
+
+mod jsdoc;
+pub use jsdoc::*;
+
+mod lexer;
+pub use lexer::*;
+
+mod parser;
+pub use parser::*;
+
+mod scope;
+pub use scope::*;
+
+mod span;
+pub use span::*;
+
+mod syntax;
+pub use syntax::*;
+
+mod tree;
+pub use tree::*;
+
+mod visit;
+pub use visit::*;
@@ -0,0 +1,542 @@
+use super::{tree::parse_top_level, *};
+
+impl SyntaxTree {
+    /// Updates the tree after the bytes in `edit` were replaced with `new_len` bytes of new
+    /// text. `txt` is the document *after* the edit.
+    ///
+    /// Only the tokens around the edit are lexed again, stopping as soon as the lexer produces
+    /// a token which the old tree already had, but never before the end of the edited line.
+    /// Likewise, only the top level statements around the edit are parsed again; once the parser
+    /// lands on the start of an untouched statement, the rest of the old statements are shifted
+    /// over and reused. The result is always the same as a full [SyntaxTree::parse].
+    pub fn edit(&mut self, txt: &str, edit: Span, new_len: usize) {
+        let delta = new_len as isize - (edit.end - edit.start) as isize;
+        let new_edit_end = edit.start + new_len;
+        let shift = |span: Span| span.shifted(delta);
+
+        // --- relex ---
+        // back up a token, since the edit might join onto the token before it.
+        let first_token = self
+            .tokens
+            .partition_point(|v| v.span.end < edit.start)
+            .saturating_sub(1);
+        let relex_start = self
+            .tokens
+            .get(first_token)
+            .map_or(0, |v| v.span.start.min(edit.start));
+
+        // whether `#macro` and `#region` are directives depends on whether anything comes before
+        // them on their line, so an edit can change the meaning of a token later on its line
+        // even when the tokens in between lex the same.
+        let line_end = txt[new_edit_end..]
+            .find('\n')
+            .map_or(txt.len(), |v| new_edit_end + v);
+
+        let mut old_idx = self.tokens.partition_point(|v| v.span.start < edit.end);
+        let mut tokens = self.tokens[..first_token].to_vec();
+        let mut resync = None;
+
+        for token in Lexer::new(txt, relex_start) {
+            if token.span.start >= line_end {
+                while self
+                    .tokens
+                    .get(old_idx)
+                    .is_some_and(|v| shift(v.span).start < token.span.start)
+                {
+                    old_idx += 1;
+                }
+
+                if let Some(old) = self.tokens.get(old_idx) {
+                    if old.kind == token.kind && shift(old.span) == token.span {
+                        resync = Some(token.span.start);
+                        break;
+                    }
+                }
+            }
+            tokens.push(token);
+        }
+
+        if resync.is_some() {
+            tokens.extend(self.tokens[old_idx..].iter().map(|v| Token {
+                kind: v.kind,
+                span: shift(v.span),
+            }));
+        }
+
+        // --- reparse ---
+        // the statement before the edit gets parsed again too, since whether it ended where it
+        // did depended on the token after it.
+        let first_stmt = self
+            .stmts
+            .partition_point(|v| v.span.end < relex_start)
+            .saturating_sub(1);
+        let reparse_start = self
+            .stmts
+            .get(first_stmt)
+            .map_or(relex_start, |v| v.span.start.min(relex_start));
+
+        let significant: Vec<_> = tokens
+            .iter()
+            .filter(|v| v.kind.is_trivia() == false)
+            .copied()
+            .collect();
+        let parser_start = significant.partition_point(|v| v.span.start < reparse_start);
+
+        let old_stmts = std::mem::take(&mut self.stmts);
+        let old_error_counts = std::mem::take(&mut self.error_counts);
+        let old_errors = std::mem::take(&mut self.errors);
+
+        let kept_errors: usize = old_error_counts[..first_stmt].iter().sum();
+        let mut stmts = old_stmts[..first_stmt].to_vec();
+        let mut error_counts = old_error_counts[..first_stmt].to_vec();
+
+        let mut parser = Parser::new_at(txt, &significant, parser_start);
+        let mut reuse_from = None;
+        let mut old_stmt = first_stmt;
+
+        while parser.at_end() == false {
+            let offset = significant[parser.pos()].span.start;
+
+            if resync.is_some_and(|resync| offset >= resync) {
+                // statements which started before the end of the edit can never be reused.
+                while old_stmts
+                    .get(old_stmt)
+                    .is_some_and(|v| v.span.start < edit.end || shift(v.span).start < offset)
+                {
+                    old_stmt += 1;
+                }
+
+                if old_stmts
+                    .get(old_stmt)
+                    .is_some_and(|v| shift(v.span).start == offset)
+                {
+                    reuse_from = Some(old_stmt);
+                    break;
+                }
+            }
+
+            parse_top_level(&mut parser, &mut stmts, &mut error_counts);
+        }
+
+        let mut errors = old_errors[..kept_errors].to_vec();
+        errors.extend(parser.finish());
+
+        if let Some(reuse_from) = reuse_from {
+            let skipped_errors: usize = old_error_counts[..reuse_from].iter().sum();
+            errors.extend(old_errors[skipped_errors..].iter().map(|v| ParseError {
+                message: v.message.clone(),
+                span: shift(v.span),
+            }));
+
+            for mut stmt in old_stmts.into_iter().skip(reuse_from) {
+                stmt.shift(delta);
+                stmts.push(stmt);
+            }
+            error_counts.extend_from_slice(&old_error_counts[reuse_from..]);
+        }
+
+        self.tokens = tokens;
+        self.stmts = stmts;
+        self.errors = errors;
+        self.error_counts = error_counts;
+        self.line_index = LineIndex::new(txt);
+    }
+}
+
+impl Span {
+    fn shifted(self, delta: isize) -> Span {
+        Span {
+            start: self.start.checked_add_signed(delta).unwrap(),
+            end: self.end.checked_add_signed(delta).unwrap(),
+        }
+    }
+}
+
+/// Moves every span in a node by the same amount.
+trait Shift {
+    fn shift(&mut self, delta: isize);
+}
+
+impl Shift for Span {
+    fn shift(&mut self, delta: isize) {
+        *self = self.shifted(delta);
+    }
+}
+
+impl<T: Shift> Shift for Option<T> {
+    fn shift(&mut self, delta: isize) {
+        if let Some(v) = self {
+            v.shift(delta);
+        }
+    }
+}
+
+impl<T: Shift> Shift for Box<T> {
+    fn shift(&mut self, delta: isize) {
+        self.as_mut().shift(delta);
+    }
+}
+
+impl<T: Shift> Shift for Vec<T> {
+    fn shift(&mut self, delta: isize) {
+        for v in self {
+            v.shift(delta);
+        }
+    }
+}
+
+impl Shift for Ident {
+    fn shift(&mut self, delta: isize) {
+        self.span.shift(delta);
+    }
+}
+
+impl Shift for Block {
+    fn shift(&mut self, delta: isize) {
+        self.span.shift(delta);
+        self.stmts.shift(delta);
+    }
+}
+
+impl Shift for VarDecl {
+    fn shift(&mut self, delta: isize) {
+        self.name.shift(delta);
+        self.value.shift(delta);
+    }
+}
+
+impl Shift for Param {
+    fn shift(&mut self, delta: isize) {
+        self.name.shift(delta);
+        self.default.shift(delta);
+    }
+}
+
+impl Shift for FunctionDecl {
+    fn shift(&mut self, delta: isize) {
+        self.name.shift(delta);
+        self.params.shift(delta);
+        self.params_span.shift(delta);
+        self.inherits.shift(delta);
+        self.body.shift(delta);
+        self.span.shift(delta);
+    }
+}
+
+impl Shift for EnumMember {
+    fn shift(&mut self, delta: isize) {
+        self.name.shift(delta);
+        self.value.shift(delta);
+    }
+}
+
+impl Shift for SwitchCase {
+    fn shift(&mut self, delta: isize) {
+        self.value.shift(delta);
+        self.body.shift(delta);
+        self.span.shift(delta);
+    }
+}
+
+impl Shift for StructField {
+    fn shift(&mut self, delta: isize) {
+        self.name.shift(delta);
+        self.value.shift(delta);
+    }
+}
+
+impl Shift for Stmt {
+    fn shift(&mut self, delta: isize) {
+        self.span.shift(delta);
+
+        match &mut self.kind {
+            StmtKind::Expr(expr) | StmtKind::Throw(expr) | StmtKind::Delete(expr) => {
+                expr.shift(delta)
+            }
+            StmtKind::Assign { target, value, .. } => {
+                target.shift(delta);
+                value.shift(delta);
+            }
+            StmtKind::Var(decls) | StmtKind::Static(decls) => decls.shift(delta),
+            StmtKind::GlobalVar(names) => names.shift(delta),
+            StmtKind::Function(func) => func.shift(delta),
+            StmtKind::Enum(decl) => {
+                decl.name.shift(delta);
+                decl.members.shift(delta);
+            }
+            StmtKind::Macro(decl) => {
+                decl.config.shift(delta);
+                decl.name.shift(delta);
+                decl.body_span.shift(delta);
+            }
+            StmtKind::Block(block) => block.shift(delta),
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                condition.shift(delta);
+                then_branch.shift(delta);
+                else_branch.shift(delta);
+            }
+            StmtKind::While { condition, body } | StmtKind::DoUntil { body, condition } => {
+                condition.shift(delta);
+                body.shift(delta);
+            }
+            StmtKind::Repeat { count, body } => {
+                count.shift(delta);
+                body.shift(delta);
+            }
+            StmtKind::For {
+                init,
+                condition,
+                step,
+                body,
+            } => {
+                init.shift(delta);
+                condition.shift(delta);
+                step.shift(delta);
+                body.shift(delta);
+            }
+            StmtKind::With { target, body } => {
+                target.shift(delta);
+                body.shift(delta);
+            }
+            StmtKind::Switch { value, cases } => {
+                value.shift(delta);
+                cases.shift(delta);
+            }
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                body.shift(delta);
+                if let Some(catch) = catch {
+                    catch.binding.shift(delta);
+                    catch.body.shift(delta);
+                }
+                finally.shift(delta);
+            }
+            StmtKind::Return(value) => value.shift(delta),
+            StmtKind::Exit
+            | StmtKind::Break
+            | StmtKind::Continue
+            | StmtKind::Empty
+            | StmtKind::Error => {}
+        }
+    }
+}
+
+impl Shift for Expr {
+    fn shift(&mut self, delta: isize) {
+        self.span.shift(delta);
+
+        match &mut self.kind {
+            ExprKind::Ident(ident) => ident.shift(delta),
+            ExprKind::Number(_) | ExprKind::String(_) | ExprKind::Error => {}
+            ExprKind::TemplateString(exprs) | ExprKind::Array(exprs) => exprs.shift(delta),
+            ExprKind::Struct(fields) => fields.shift(delta),
+            ExprKind::Function(func) => func.shift(delta),
+            ExprKind::Grouping(expr)
+            | ExprKind::Unary { expr, .. }
+            | ExprKind::Postfix { expr, .. }
+            | ExprKind::New(expr) => expr.shift(delta),
+            ExprKind::Binary { lhs, rhs, .. } => {
+                lhs.shift(delta);
+                rhs.shift(delta);
+            }
+            ExprKind::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                condition.shift(delta);
+                then_branch.shift(delta);
+                else_branch.shift(delta);
+            }
+            ExprKind::Call {
+                callee,
+                args,
+                args_span,
+            } => {
+                callee.shift(delta);
+                args.shift(delta);
+                args_span.shift(delta);
+            }
+            ExprKind::Field { expr, field } => {
+                expr.shift(delta);
+                field.shift(delta);
+            }
+            ExprKind::Index { expr, indices, .. } => {
+                expr.shift(delta);
+                indices.shift(delta);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"/// @function scr_spawn_wave(count)
+function scr_spawn_wave(_count = 3) {
+    var spawned = [];
+    repeat (_count) {
+        array_push(spawned, instance_create_depth(random(room_width), 0, 0, obj_enemy));
+    }
+    /* spawned enemies
+       get a little nudge */
+    with (obj_enemy) { speed = 2; }
+    return spawned;
+}
+
+enum EState { Idle, Walk = 5, Run }
+#macro WAVE_SIZE 10
+global.wave = scr_spawn_wave(WAVE_SIZE);
+show_debug_message($"spawned {array_length(global.wave)} enemies");
+"#;
+
+    /// Applies an edit to both the text and the tree, then checks the tree against a fresh
+    /// parse of the same text.
+    fn check_edit(txt: &mut String, tree: &mut SyntaxTree, start: usize, end: usize, new: &str) {
+        txt.replace_range(start..end, new);
+        tree.edit(txt, Span::new(start, end), new.len());
+
+        assert_eq!(
+            *tree,
+            SyntaxTree::parse(txt),
+            "edit of {}..{} to {:?} gave\n{}",
+            start,
+            end,
+            new,
+            txt
+        );
+    }
+
+    #[test]
+    fn simple_edits() {
+        let mut txt = SCRIPT.to_string();
+        let mut tree = SyntaxTree::parse(&txt);
+
+        // typing a character into an identifier
+        let pos = txt.find("speed").unwrap() + 5;
+        check_edit(&mut txt, &mut tree, pos, pos, "s");
+
+        // deleting a whole statement
+        let start = txt.find("enum").unwrap();
+        let end = txt.find("#macro").unwrap();
+        check_edit(&mut txt, &mut tree, start, end, "");
+
+        // a newline which changes nothing
+        check_edit(&mut txt, &mut tree, 0, 0, "\n");
+
+        // an edit right at the end
+        let len = txt.len();
+        check_edit(&mut txt, &mut tree, len, len, "x = 1");
+    }
+
+    #[test]
+    fn edits_which_spill_over() {
+        let mut txt = SCRIPT.to_string();
+        let mut tree = SyntaxTree::parse(&txt);
+
+        // opening a block comment swallows the rest of the file...
+        let pos = txt.find("enum").unwrap();
+        check_edit(&mut txt, &mut tree, pos, pos, "/*");
+        // ...and closing it brings it back.
+        check_edit(&mut txt, &mut tree, pos, pos + 2, "");
+
+        // opening a string
+        let pos = txt.find("spawned;").unwrap();
+        check_edit(&mut txt, &mut tree, pos, pos, "\"");
+        check_edit(&mut txt, &mut tree, pos, pos + 1, "");
+
+        // removing a `}` pulls the following statements into the function body
+        let pos = txt.find("}\n\nenum").unwrap();
+        check_edit(&mut txt, &mut tree, pos, pos + 1, "");
+        check_edit(&mut txt, &mut tree, pos, pos, "}");
+
+        // joining a statement onto the one before it
+        let pos = txt.find("global.wave").unwrap() - 1;
+        check_edit(&mut txt, &mut tree, pos - 1, pos + 1, " ");
+    }
+
+    #[test]
+    fn edits_before_directives() {
+        let mut txt = "x #macro WAVE_SIZE 10\ny = 2;\n".to_string();
+        let mut tree = SyntaxTree::parse(&txt);
+        let has = |tree: &SyntaxTree, kind| tree.tokens.iter().any(|v| v.kind == kind);
+
+        // taking away what came before a `#macro` on its line makes it a macro, even though the
+        // whitespace after the edit lexes just the same...
+        check_edit(&mut txt, &mut tree, 0, 1, "");
+        assert!(has(&tree, TokenKind::Macro));
+        // ...and putting something back stops it being one
+        check_edit(&mut txt, &mut tree, 0, 0, "x");
+        assert!(has(&tree, TokenKind::Macro) == false);
+
+        let mut txt = " #region enemies\ny = 2;\n".to_string();
+        let mut tree = SyntaxTree::parse(&txt);
+        check_edit(&mut txt, &mut tree, 0, 0, "y");
+        assert!(has(&tree, TokenKind::Region) == false);
+        check_edit(&mut txt, &mut tree, 0, 1, "");
+        assert!(has(&tree, TokenKind::Region));
+    }
+
+    #[test]
+    fn random_edits_match_full_parse() {
+        const SNIPPETS: [&str; 17] = [
+            "",
+            "a",
+            "(",
+            ")",
+            "{",
+            "}",
+            ";",
+            "\n",
+            "\"",
+            "/*",
+            "*/",
+            "//",
+            ", ",
+            "var ",
+            ".",
+            "#macro X 1\n",
+            " #region\n",
+        ];
+
+        let mut txt = SCRIPT.to_string();
+        let mut tree = SyntaxTree::parse(&txt);
+
+        // a small deterministic lcg, so failures can be reproduced.
+        let mut seed: u64 = 0x5eed;
+        let mut next = |max: usize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % max.max(1)
+        };
+
+        for _ in 0..2000 {
+            let mut start = next(txt.len() + 1);
+            while txt.is_char_boundary(start) == false {
+                start -= 1;
+            }
+            let mut end = (start + next(8)).min(txt.len());
+            while txt.is_char_boundary(end) == false {
+                end -= 1;
+            }
+            let snippet = SNIPPETS[next(SNIPPETS.len())];
+
+            check_edit(&mut txt, &mut tree, start, end, snippet);
+
+            // don't let the document drift away into nothing.
+            if txt.len() < SCRIPT.len() / 2 {
+                txt = SCRIPT.to_string();
+                tree = SyntaxTree::parse(&txt);
+            }
+        }
+    }
+}
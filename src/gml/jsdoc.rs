@@ -0,0 +1,219 @@
+use super::*;
+
+/// The `///` documentation above a function, in the JSDoc style which Feather uses.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct JsDoc {
+    /// The signature given by `@function`, if there was one.
+    pub function: Option<String>,
+    pub description: String,
+    pub params: Vec<JsDocParam>,
+    pub returns: Option<JsDocReturns>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct JsDocParam {
+    pub name: String,
+    pub gm_type: Option<String>,
+    pub description: String,
+    /// Written as `[name]`.
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct JsDocReturns {
+    pub gm_type: Option<String>,
+    pub description: String,
+}
+
+/// The tag which untagged lines continue the description of.
+enum Continuing {
+    Description,
+    Param,
+    Returns,
+    Nothing,
+}
+
+impl JsDoc {
+    pub fn parse<'a>(lines: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut output = Self::default();
+        let mut continuing = Continuing::Description;
+
+        for line in lines {
+            let line = line.trim();
+            let Some(tagged) = line.strip_prefix('@') else {
+                let description = match continuing {
+                    Continuing::Description => Some(&mut output.description),
+                    Continuing::Param => output.params.last_mut().map(|v| &mut v.description),
+                    Continuing::Returns => output.returns.as_mut().map(|v| &mut v.description),
+                    Continuing::Nothing => None,
+                };
+                if let Some(description) = description {
+                    append_line(description, line);
+                }
+                continue;
+            };
+
+            let (tag, rest) = tagged
+                .split_once(char::is_whitespace)
+                .unwrap_or((tagged, ""));
+            let rest = rest.trim();
+
+            continuing = match tag {
+                "function" | "func" => {
+                    output.function = Some(rest.to_string());
+                    Continuing::Nothing
+                }
+                "description" | "desc" => {
+                    append_line(&mut output.description, rest);
+                    Continuing::Description
+                }
+                "param" | "parameter" | "arg" | "argument" => {
+                    let (gm_type, rest) = split_type(rest);
+                    let (name, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+                    // `[name]` and `[name=default]` mark optional parameters
+                    let optional = name.starts_with('[');
+                    let name = name.trim_start_matches('[').trim_end_matches(']');
+                    let name = name.split('=').next().unwrap_or_default();
+
+                    output.params.push(JsDocParam {
+                        name: name.to_string(),
+                        gm_type,
+                        description: trim_description(rest).to_string(),
+                        optional,
+                    });
+                    Continuing::Param
+                }
+                "returns" | "return" => {
+                    let (gm_type, rest) = split_type(rest);
+                    output.returns = Some(JsDocReturns {
+                        gm_type,
+                        description: trim_description(rest).to_string(),
+                    });
+                    Continuing::Returns
+                }
+                _ => Continuing::Nothing,
+            };
+        }
+
+        output
+    }
+
+    pub fn param(&self, name: &str) -> Option<&JsDocParam> {
+        self.params.iter().find(|v| v.name == name)
+    }
+}
+
+/// Splits the `{Type}` off the front of a tag.
+fn split_type(rest: &str) -> (Option<String>, &str) {
+    match rest.strip_prefix('{').and_then(|v| v.split_once('}')) {
+        Some((gm_type, rest)) => (Some(gm_type.trim().to_string()), rest.trim_start()),
+        None => (None, rest),
+    }
+}
+
+/// People often write `@param x - the x`, but the dash isn't part of the description.
+fn trim_description(description: &str) -> &str {
+    description.trim().trim_start_matches('-').trim_start()
+}
+
+fn append_line(description: &mut String, line: &str) {
+    if line.is_empty() {
+        return;
+    }
+    if description.is_empty() == false {
+        description.push('\n');
+    }
+    description.push_str(line);
+}
+
+impl SyntaxTree {
+    /// Parses the `///` comments directly above `offset`. A blank line between the comments and
+    /// `offset` means they aren't about whatever is there.
+    pub fn doc_comment_before(&self, txt: &str, offset: usize) -> Option<JsDoc> {
+        let end = self.tokens.partition_point(|v| v.span.end <= offset);
+
+        let mut lines = vec![];
+        for token in self.tokens[..end].iter().rev() {
+            let lexeme = &txt[token.span.start..token.span.end];
+            match token.kind {
+                TokenKind::DocComment => lines.push(&lexeme[3..]),
+                TokenKind::Whitespace if lexeme.matches('\n').count() <= 1 => {}
+                _ => break,
+            }
+        }
+
+        if lines.is_empty() {
+            return None;
+        }
+        lines.reverse();
+
+        Some(JsDoc::parse(lines))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jsdoc() {
+        let txt = r#"
+// not docs
+
+/// @function scr_spawn(x, y, [count])
+/// @description Spawns some enemies
+///              around a point.
+/// @param {Real} x - The x position
+/// @param {Real} y The y position
+/// @param {Real} [count=1] How many to spawn.
+///        Defaults to one.
+/// @returns {Array<Id.Instance>} The spawned enemies
+/// @pure
+function scr_spawn(x, y, count = 1) {}
+"#;
+        let tree = SyntaxTree::parse(txt);
+        let offset = txt.find("\nfunction").unwrap() + 1;
+        let docs = tree.doc_comment_before(txt, offset).unwrap();
+
+        assert_eq!(docs.function.as_deref(), Some("scr_spawn(x, y, [count])"));
+        assert_eq!(docs.description, "Spawns some enemies\naround a point.");
+        assert_eq!(
+            docs.params,
+            vec![
+                JsDocParam {
+                    name: "x".to_string(),
+                    gm_type: Some("Real".to_string()),
+                    description: "The x position".to_string(),
+                    optional: false,
+                },
+                JsDocParam {
+                    name: "y".to_string(),
+                    gm_type: Some("Real".to_string()),
+                    description: "The y position".to_string(),
+                    optional: false,
+                },
+                JsDocParam {
+                    name: "count".to_string(),
+                    gm_type: Some("Real".to_string()),
+                    description: "How many to spawn.\nDefaults to one.".to_string(),
+                    optional: true,
+                },
+            ]
+        );
+        assert_eq!(
+            docs.returns,
+            Some(JsDocReturns {
+                gm_type: Some("Array<Id.Instance>".to_string()),
+                description: "The spawned enemies".to_string(),
+            })
+        );
+
+        let txt = "/// Orphaned\n\nfunction f() {}";
+        let tree = SyntaxTree::parse(txt);
+        assert_eq!(
+            tree.doc_comment_before(txt, txt.find("function").unwrap()),
+            None
+        );
+    }
+}
@@ -0,0 +1,630 @@
+use super::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    // trivia
+    Whitespace,
+    LineComment,
+    DocComment,
+    BlockComment,
+    /// `#region` and `#endregion` lines, which are purely for the editor.
+    Region,
+
+    /// A full `#macro` line, including any `\` continued lines.
+    Macro,
+    Identifier,
+    Number,
+    String,
+    /// `$"..."` strings. The embedded `{expr}` segments are parsed separately.
+    TemplateString,
+
+    // keywords
+    Var,
+    GlobalVar,
+    Static,
+    Function,
+    Constructor,
+    Return,
+    Exit,
+    If,
+    Then,
+    Else,
+    While,
+    Do,
+    Until,
+    Repeat,
+    For,
+    With,
+    Switch,
+    Case,
+    Default,
+    Break,
+    Continue,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    New,
+    Delete,
+    Enum,
+    Begin,
+    End,
+    And,
+    Or,
+    Xor,
+    Not,
+    Mod,
+    Div,
+
+    // punctuation
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    LeftBrace,
+    RightBrace,
+    /// `[|`
+    ListAccessor,
+    /// `[?`
+    MapAccessor,
+    /// `[#`
+    GridAccessor,
+    /// `[@`
+    ArrayAccessor,
+    /// `[$`
+    StructAccessor,
+    Comma,
+    Dot,
+    Semicolon,
+    Colon,
+    Question,
+    QuestionQuestion,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    PlusPlus,
+    MinusMinus,
+    Bang,
+    Tilde,
+    Amp,
+    Pipe,
+    Caret,
+    LessLess,
+    GreaterGreater,
+    AmpAmp,
+    PipePipe,
+    CaretCaret,
+    Equal,
+    EqualEqual,
+    BangEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    ColonEqual,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    AmpEqual,
+    PipeEqual,
+    CaretEqual,
+    QuestionQuestionEqual,
+
+    Unknown,
+}
+
+impl TokenKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            TokenKind::Whitespace
+                | TokenKind::LineComment
+                | TokenKind::DocComment
+                | TokenKind::BlockComment
+                | TokenKind::Region
+        )
+    }
+
+    pub fn is_comment(self) -> bool {
+        matches!(
+            self,
+            TokenKind::LineComment | TokenKind::DocComment | TokenKind::BlockComment
+        )
+    }
+
+    /// Identifiers and keywords, which are what a user is typing when they want completions.
+    pub fn is_word(self) -> bool {
+        self == TokenKind::Identifier || self.is_keyword()
+    }
+
+    pub fn is_keyword(self) -> bool {
+        matches!(
+            self,
+            TokenKind::Var
+                | TokenKind::GlobalVar
+                | TokenKind::Static
+                | TokenKind::Function
+                | TokenKind::Constructor
+                | TokenKind::Return
+                | TokenKind::Exit
+                | TokenKind::If
+                | TokenKind::Then
+                | TokenKind::Else
+                | TokenKind::While
+                | TokenKind::Do
+                | TokenKind::Until
+                | TokenKind::Repeat
+                | TokenKind::For
+                | TokenKind::With
+                | TokenKind::Switch
+                | TokenKind::Case
+                | TokenKind::Default
+                | TokenKind::Break
+                | TokenKind::Continue
+                | TokenKind::Try
+                | TokenKind::Catch
+                | TokenKind::Finally
+                | TokenKind::Throw
+                | TokenKind::New
+                | TokenKind::Delete
+                | TokenKind::Enum
+                | TokenKind::Begin
+                | TokenKind::End
+                | TokenKind::And
+                | TokenKind::Or
+                | TokenKind::Xor
+                | TokenKind::Not
+                | TokenKind::Mod
+                | TokenKind::Div
+        )
+    }
+
+    fn keyword(ident: &str) -> Option<TokenKind> {
+        let output = match ident {
+            "var" => TokenKind::Var,
+            "globalvar" => TokenKind::GlobalVar,
+            "static" => TokenKind::Static,
+            "function" => TokenKind::Function,
+            "constructor" => TokenKind::Constructor,
+            "return" => TokenKind::Return,
+            "exit" => TokenKind::Exit,
+            "if" => TokenKind::If,
+            "then" => TokenKind::Then,
+            "else" => TokenKind::Else,
+            "while" => TokenKind::While,
+            "do" => TokenKind::Do,
+            "until" => TokenKind::Until,
+            "repeat" => TokenKind::Repeat,
+            "for" => TokenKind::For,
+            "with" => TokenKind::With,
+            "switch" => TokenKind::Switch,
+            "case" => TokenKind::Case,
+            "default" => TokenKind::Default,
+            "break" => TokenKind::Break,
+            "continue" => TokenKind::Continue,
+            "try" => TokenKind::Try,
+            "catch" => TokenKind::Catch,
+            "finally" => TokenKind::Finally,
+            "throw" => TokenKind::Throw,
+            "new" => TokenKind::New,
+            "delete" => TokenKind::Delete,
+            "enum" => TokenKind::Enum,
+            "begin" => TokenKind::Begin,
+            "end" => TokenKind::End,
+            "and" => TokenKind::And,
+            "or" => TokenKind::Or,
+            "xor" => TokenKind::Xor,
+            "not" => TokenKind::Not,
+            "mod" => TokenKind::Mod,
+            "div" => TokenKind::Div,
+            _ => return None,
+        };
+
+        Some(output)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Lexes an entire document. The lexer never fails: anything it doesn't understand becomes
+/// [TokenKind::Unknown], and unterminated strings and comments run to the end of the input.
+pub fn lex(txt: &str) -> Vec<Token> {
+    Lexer::new(txt, 0).collect()
+}
+
+pub struct Lexer<'a> {
+    txt: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates a lexer which starts at the byte offset `pos`. `pos` must be on a token boundary.
+    pub fn new(txt: &'a str, pos: usize) -> Self {
+        Self { txt, pos }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.txt[self.pos..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.txt[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let chr = self.peek()?;
+        self.pos += chr.len_utf8();
+        Some(chr)
+    }
+
+    fn eat(&mut self, chr: char) -> bool {
+        if self.peek() == Some(chr) {
+            self.pos += chr.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_while(&mut self, f: impl Fn(char) -> bool) {
+        while let Some(chr) = self.peek() {
+            if f(chr) == false {
+                break;
+            }
+            self.pos += chr.len_utf8();
+        }
+    }
+
+    fn eat_line(&mut self) {
+        self.eat_while(|c| c != '\n' && c != '\r');
+    }
+
+    fn at_line_start(&self, start: usize) -> bool {
+        self.txt[..start]
+            .chars()
+            .rev()
+            .take_while(|c| *c != '\n')
+            .all(|c| c == ' ' || c == '\t' || c == '\r')
+    }
+
+    fn directive(&mut self, start: usize) -> TokenKind {
+        let rest = &self.txt[start + 1..];
+        let word_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+
+        match &rest[..word_len] {
+            "macro" if self.at_line_start(start) => {
+                // macros run to the end of the line, but a trailing `\` continues them.
+                loop {
+                    self.eat_line();
+                    let line = &self.txt[start..self.pos];
+                    if line.trim_end().ends_with('\\') && self.peek().is_some() {
+                        self.eat('\r');
+                        self.eat('\n');
+                    } else {
+                        break;
+                    }
+                }
+                TokenKind::Macro
+            }
+            "region" | "endregion" if self.at_line_start(start) => {
+                self.eat_line();
+                TokenKind::Region
+            }
+            _ => TokenKind::Unknown,
+        }
+    }
+
+    fn string(&mut self, quote: char) {
+        while let Some(chr) = self.bump() {
+            match chr {
+                '\\' => {
+                    self.bump();
+                }
+                '\n' => {
+                    // regular strings cannot span lines, so stop here rather than eating the
+                    // rest of the file.
+                    self.pos -= 1;
+                    return;
+                }
+                c if c == quote => return,
+                _ => {}
+            }
+        }
+    }
+
+    fn verbatim_string(&mut self, quote: char) {
+        while let Some(chr) = self.bump() {
+            if chr == quote {
+                return;
+            }
+        }
+    }
+
+    fn template_string(&mut self) {
+        let mut brace_depth = 0usize;
+        while let Some(chr) = self.bump() {
+            match chr {
+                '\\' if brace_depth == 0 => {
+                    self.bump();
+                }
+                '{' => brace_depth += 1,
+                '}' => brace_depth = brace_depth.saturating_sub(1),
+                '"' if brace_depth > 0 => self.string('"'),
+                '"' => return,
+                '\n' if brace_depth == 0 => {
+                    self.pos -= 1;
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn number(&mut self, first: char) {
+        if first == '0' && matches!(self.peek(), Some('x') | Some('X')) {
+            self.bump();
+            self.eat_while(|c| c.is_ascii_hexdigit() || c == '_');
+        } else if first == '0' && matches!(self.peek(), Some('b') | Some('B')) {
+            self.bump();
+            self.eat_while(|c| c == '0' || c == '1' || c == '_');
+        } else if first == '$' {
+            self.eat_while(|c| c.is_ascii_hexdigit() || c == '_');
+        } else {
+            self.eat_while(|c| c.is_ascii_digit() || c == '_');
+            if self.peek() == Some('.') && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) {
+                self.bump();
+                self.eat_while(|c| c.is_ascii_digit() || c == '_');
+            }
+        }
+    }
+
+    fn next_kind(&mut self, start: usize) -> Option<TokenKind> {
+        let chr = self.bump()?;
+
+        let kind = match chr {
+            c if c.is_whitespace() => {
+                self.eat_while(char::is_whitespace);
+                TokenKind::Whitespace
+            }
+            '/' if self.eat('/') => {
+                let doc = self.peek() == Some('/') && self.peek_nth(1) != Some('/');
+                self.eat_line();
+                if doc {
+                    TokenKind::DocComment
+                } else {
+                    TokenKind::LineComment
+                }
+            }
+            '/' if self.eat('*') => {
+                match self.txt[self.pos..].find("*/") {
+                    Some(end) => self.pos += end + 2,
+                    None => self.pos = self.txt.len(),
+                }
+                TokenKind::BlockComment
+            }
+            '#' => self.directive(start),
+            '"' => {
+                self.string('"');
+                TokenKind::String
+            }
+            '\'' => {
+                self.string('\'');
+                TokenKind::String
+            }
+            '@' if matches!(self.peek(), Some('"') | Some('\'')) => {
+                let quote = self.bump().unwrap();
+                self.verbatim_string(quote);
+                TokenKind::String
+            }
+            '$' if self.peek() == Some('"') => {
+                self.bump();
+                self.template_string();
+                TokenKind::TemplateString
+            }
+            '$' if self.peek().is_some_and(|c| c.is_ascii_hexdigit()) => {
+                self.number('$');
+                TokenKind::Number
+            }
+            c if c.is_ascii_digit() => {
+                self.number(c);
+                TokenKind::Number
+            }
+            '.' if self.peek().is_some_and(|c| c.is_ascii_digit()) => {
+                self.eat_while(|c| c.is_ascii_digit() || c == '_');
+                TokenKind::Number
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                self.eat_while(|c| c.is_alphanumeric() || c == '_');
+                TokenKind::keyword(&self.txt[start..self.pos]).unwrap_or(TokenKind::Identifier)
+            }
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            '[' => match self.peek() {
+                Some('|') => {
+                    self.bump();
+                    TokenKind::ListAccessor
+                }
+                Some('?') => {
+                    self.bump();
+                    TokenKind::MapAccessor
+                }
+                Some('#') => {
+                    self.bump();
+                    TokenKind::GridAccessor
+                }
+                Some('@') => {
+                    self.bump();
+                    TokenKind::ArrayAccessor
+                }
+                Some('$') => {
+                    self.bump();
+                    TokenKind::StructAccessor
+                }
+                _ => TokenKind::LeftBracket,
+            },
+            ']' => TokenKind::RightBracket,
+            '{' => TokenKind::LeftBrace,
+            '}' => TokenKind::RightBrace,
+            ',' => TokenKind::Comma,
+            '.' => TokenKind::Dot,
+            ';' => TokenKind::Semicolon,
+            ':' if self.eat('=') => TokenKind::ColonEqual,
+            ':' => TokenKind::Colon,
+            '?' if self.eat('?') => {
+                if self.eat('=') {
+                    TokenKind::QuestionQuestionEqual
+                } else {
+                    TokenKind::QuestionQuestion
+                }
+            }
+            '?' => TokenKind::Question,
+            '+' if self.eat('+') => TokenKind::PlusPlus,
+            '+' if self.eat('=') => TokenKind::PlusEqual,
+            '+' => TokenKind::Plus,
+            '-' if self.eat('-') => TokenKind::MinusMinus,
+            '-' if self.eat('=') => TokenKind::MinusEqual,
+            '-' => TokenKind::Minus,
+            '*' if self.eat('=') => TokenKind::StarEqual,
+            '*' => TokenKind::Star,
+            '/' if self.eat('=') => TokenKind::SlashEqual,
+            '/' => TokenKind::Slash,
+            '%' if self.eat('=') => TokenKind::PercentEqual,
+            '%' => TokenKind::Percent,
+            '!' if self.eat('=') => TokenKind::BangEqual,
+            '!' => TokenKind::Bang,
+            '~' => TokenKind::Tilde,
+            '&' if self.eat('&') => TokenKind::AmpAmp,
+            '&' if self.eat('=') => TokenKind::AmpEqual,
+            '&' => TokenKind::Amp,
+            '|' if self.eat('|') => TokenKind::PipePipe,
+            '|' if self.eat('=') => TokenKind::PipeEqual,
+            '|' => TokenKind::Pipe,
+            '^' if self.eat('^') => TokenKind::CaretCaret,
+            '^' if self.eat('=') => TokenKind::CaretEqual,
+            '^' => TokenKind::Caret,
+            '=' if self.eat('=') => TokenKind::EqualEqual,
+            '=' => TokenKind::Equal,
+            '<' if self.eat('<') => TokenKind::LessLess,
+            '<' if self.eat('=') => TokenKind::LessEqual,
+            '<' if self.eat('>') => TokenKind::BangEqual,
+            '<' => TokenKind::Less,
+            '>' if self.eat('>') => TokenKind::GreaterGreater,
+            '>' if self.eat('=') => TokenKind::GreaterEqual,
+            '>' => TokenKind::Greater,
+            _ => TokenKind::Unknown,
+        };
+
+        Some(kind)
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.pos;
+        self.next_kind(start).map(|kind| Token {
+            kind,
+            span: Span::new(start, self.pos),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(txt: &str) -> Vec<TokenKind> {
+        lex(txt)
+            .into_iter()
+            .map(|v| v.kind)
+            .filter(|v| v.is_trivia() == false)
+            .collect()
+    }
+
+    #[test]
+    fn basic_tokens() {
+        assert_eq!(
+            kinds("var a = b[| 0] += 1.5;"),
+            vec![
+                TokenKind::Var,
+                TokenKind::Identifier,
+                TokenKind::Equal,
+                TokenKind::Identifier,
+                TokenKind::ListAccessor,
+                TokenKind::Number,
+                TokenKind::RightBracket,
+                TokenKind::PlusEqual,
+                TokenKind::Number,
+                TokenKind::Semicolon,
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_and_strings() {
+        let txt = "/// @param x\n// hello (\n/* multi\nline ( */ \"a // b\" @'multi\nline' $\"{x} ( {\"}\"}\"";
+        let tokens = lex(txt);
+        let kinds: Vec<_> = tokens.iter().map(|v| v.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::DocComment,
+                TokenKind::Whitespace,
+                TokenKind::LineComment,
+                TokenKind::Whitespace,
+                TokenKind::BlockComment,
+                TokenKind::Whitespace,
+                TokenKind::String,
+                TokenKind::Whitespace,
+                TokenKind::String,
+                TokenKind::Whitespace,
+                TokenKind::TemplateString,
+            ]
+        );
+        assert_eq!(tokens.last().unwrap().span.end, txt.len());
+    }
+
+    #[test]
+    fn directives() {
+        let txt = "#macro FOO 1 + \\\n 2\n#region stuff\nfoo = FOO;";
+        let tokens = lex(txt);
+        assert_eq!(tokens[0].kind, TokenKind::Macro);
+        assert_eq!(
+            &txt[tokens[0].span.start..tokens[0].span.end],
+            "#macro FOO 1 + \\\n 2"
+        );
+        assert_eq!(
+            kinds(txt),
+            vec![
+                TokenKind::Macro,
+                TokenKind::Identifier,
+                TokenKind::Equal,
+                TokenKind::Identifier,
+                TokenKind::Semicolon,
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(
+            kinds("0xFF $ff 0b101 1_000 .5 3.25"),
+            vec![TokenKind::Number; 6]
+        );
+        assert_eq!(
+            kinds("a.b"),
+            vec![TokenKind::Identifier, TokenKind::Dot, TokenKind::Identifier]
+        );
+    }
+}
@@ -0,0 +1,1029 @@
+use super::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+/// A recursive descent parser over the significant (non-trivia) tokens of a document.
+///
+/// The parser never gives up: when it finds something it cannot make sense of, it records a
+/// [ParseError], produces an `Error` node, and skips ahead so that the rest of the document
+/// still gets a tree. That matters since most of the time we're parsing half-typed code.
+pub struct Parser<'a> {
+    txt: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Parser<'a> {
+    /// Creates a parser over `tokens`, which must not contain trivia.
+    pub fn new(txt: &'a str, tokens: &'a [Token]) -> Self {
+        Self {
+            txt,
+            tokens,
+            pos: 0,
+            errors: vec![],
+        }
+    }
+
+    /// Creates a parser which starts on the `pos`th token.
+    pub fn new_at(txt: &'a str, tokens: &'a [Token], pos: usize) -> Self {
+        Self {
+            pos,
+            ..Self::new(txt, tokens)
+        }
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn finish(self) -> Vec<ParseError> {
+        self.errors
+    }
+
+    /// Parses one statement at the top level of a document. Unlike a nested statement, a stray
+    /// `}` here is consumed as an error.
+    pub fn parse_top_level(&mut self) -> Stmt {
+        if self.at(TokenKind::RightBrace) || self.at(TokenKind::End) {
+            let token = self.bump();
+            self.error("unexpected `}`", token.span);
+            return Stmt {
+                kind: StmtKind::Error,
+                span: token.span,
+            };
+        }
+
+        self.parse_stmt()
+    }
+
+    // --- token helpers ---
+
+    fn peek(&self) -> Option<TokenKind> {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<TokenKind> {
+        self.tokens.get(self.pos + n).map(|v| v.kind)
+    }
+
+    fn at(&self, kind: TokenKind) -> bool {
+        self.peek() == Some(kind)
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.tokens[self.pos];
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, kind: TokenKind) -> bool {
+        if self.at(kind) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind, what: &str) -> bool {
+        if self.eat(kind) {
+            true
+        } else {
+            self.error(format!("expected {}", what), self.cur_span());
+            false
+        }
+    }
+
+    fn error(&mut self, message: impl Into<String>, span: Span) {
+        self.errors.push(ParseError {
+            message: message.into(),
+            span,
+        });
+    }
+
+    /// The span of the current token, or an empty span at the end of the input.
+    fn cur_span(&self) -> Span {
+        match self.tokens.get(self.pos) {
+            Some(token) => token.span,
+            None => {
+                let end = self.prev_end();
+                Span::new(end, end)
+            }
+        }
+    }
+
+    fn cur_start(&self) -> usize {
+        self.cur_span().start
+    }
+
+    /// The end of the last consumed token.
+    fn prev_end(&self) -> usize {
+        self.pos
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .map(|v| v.span.end)
+            .unwrap_or_default()
+    }
+
+    fn text(&self, span: Span) -> &'a str {
+        &self.txt[span.start..span.end]
+    }
+
+    fn ident(&mut self, what: &str) -> Option<Ident> {
+        if self.at(TokenKind::Identifier) {
+            let token = self.bump();
+            Some(Ident {
+                name: self.text(token.span).to_string(),
+                span: token.span,
+            })
+        } else {
+            self.error(format!("expected {}", what), self.cur_span());
+            None
+        }
+    }
+
+    fn stmt(&self, kind: StmtKind, start: usize) -> Stmt {
+        Stmt {
+            kind,
+            span: Span::new(start, self.prev_end().max(start)),
+        }
+    }
+
+    fn expr(&self, kind: ExprKind, start: usize) -> Expr {
+        Expr {
+            kind,
+            span: Span::new(start, self.prev_end().max(start)),
+        }
+    }
+
+    // --- statements ---
+
+    fn at_block_end(&self) -> bool {
+        matches!(
+            self.peek(),
+            None | Some(TokenKind::RightBrace) | Some(TokenKind::End)
+        )
+    }
+
+    fn parse_block(&mut self) -> Block {
+        let start = self.cur_start();
+        if self.eat(TokenKind::LeftBrace) == false && self.eat(TokenKind::Begin) == false {
+            self.error("expected `{`", self.cur_span());
+            return Block {
+                stmts: vec![],
+                span: Span::new(start, start),
+            };
+        }
+
+        let stmts = self.parse_stmts_until_block_end();
+        if self.eat(TokenKind::RightBrace) == false && self.eat(TokenKind::End) == false {
+            self.error("expected `}`", self.cur_span());
+        }
+
+        Block {
+            stmts,
+            span: Span::new(start, self.prev_end()),
+        }
+    }
+
+    fn parse_stmts_until_block_end(&mut self) -> Vec<Stmt> {
+        let mut stmts = vec![];
+        while self.at_block_end() == false {
+            stmts.push(self.parse_stmt());
+        }
+        stmts
+    }
+
+    pub fn parse_stmt(&mut self) -> Stmt {
+        let start = self.cur_start();
+        let start_pos = self.pos;
+
+        let Some(kind) = self.peek() else {
+            return self.stmt(StmtKind::Empty, start);
+        };
+
+        let stmt_kind = match kind {
+            TokenKind::Semicolon => {
+                self.bump();
+                return self.stmt(StmtKind::Empty, start);
+            }
+            TokenKind::LeftBrace | TokenKind::Begin => StmtKind::Block(self.parse_block()),
+            TokenKind::Var => {
+                self.bump();
+                StmtKind::Var(self.parse_var_decls())
+            }
+            TokenKind::Static => {
+                self.bump();
+                StmtKind::Static(self.parse_var_decls())
+            }
+            TokenKind::GlobalVar => {
+                self.bump();
+                let mut names = vec![];
+                loop {
+                    if let Some(ident) = self.ident("a variable name") {
+                        names.push(ident);
+                    }
+                    if self.eat(TokenKind::Comma) == false {
+                        break;
+                    }
+                }
+                self.eat(TokenKind::Semicolon);
+                StmtKind::GlobalVar(names)
+            }
+            TokenKind::Function if self.peek_nth(1) == Some(TokenKind::Identifier) => {
+                StmtKind::Function(self.parse_function())
+            }
+            TokenKind::Enum => StmtKind::Enum(self.parse_enum()),
+            TokenKind::Macro => {
+                let token = self.bump();
+                match self.parse_macro(token) {
+                    Some(v) => StmtKind::Macro(v),
+                    None => StmtKind::Error,
+                }
+            }
+            TokenKind::If => {
+                self.bump();
+                let condition = self.parse_expr();
+                self.eat(TokenKind::Then);
+                let then_branch = Box::new(self.parse_body());
+                let else_branch = self
+                    .eat(TokenKind::Else)
+                    .then(|| Box::new(self.parse_body()));
+
+                StmtKind::If {
+                    condition,
+                    then_branch,
+                    else_branch,
+                }
+            }
+            TokenKind::While => {
+                self.bump();
+                let condition = self.parse_expr();
+                let body = Box::new(self.parse_body());
+                StmtKind::While { condition, body }
+            }
+            TokenKind::Do => {
+                self.bump();
+                let body = Box::new(self.parse_body());
+                self.expect(TokenKind::Until, "`until`");
+                let condition = self.parse_expr();
+                self.eat(TokenKind::Semicolon);
+                StmtKind::DoUntil { body, condition }
+            }
+            TokenKind::Repeat => {
+                self.bump();
+                let count = self.parse_expr();
+                let body = Box::new(self.parse_body());
+                StmtKind::Repeat { count, body }
+            }
+            TokenKind::With => {
+                self.bump();
+                let target = self.parse_expr();
+                let body = Box::new(self.parse_body());
+                StmtKind::With { target, body }
+            }
+            TokenKind::For => self.parse_for(),
+            TokenKind::Switch => self.parse_switch(),
+            TokenKind::Try => self.parse_try(),
+            TokenKind::Return => {
+                self.bump();
+                let value = self.can_start_expr().then(|| self.parse_expr());
+                self.eat(TokenKind::Semicolon);
+                StmtKind::Return(value)
+            }
+            TokenKind::Exit | TokenKind::Break | TokenKind::Continue => {
+                self.bump();
+                self.eat(TokenKind::Semicolon);
+                match kind {
+                    TokenKind::Exit => StmtKind::Exit,
+                    TokenKind::Break => StmtKind::Break,
+                    _ => StmtKind::Continue,
+                }
+            }
+            TokenKind::Throw => {
+                self.bump();
+                let value = self.parse_expr();
+                self.eat(TokenKind::Semicolon);
+                StmtKind::Throw(value)
+            }
+            TokenKind::Delete => {
+                self.bump();
+                let value = self.parse_expr();
+                self.eat(TokenKind::Semicolon);
+                StmtKind::Delete(value)
+            }
+            _ => {
+                let kind = self.parse_simple_stmt();
+                self.eat(TokenKind::Semicolon);
+                kind
+            }
+        };
+
+        // we always make progress, even if that means throwing a token away.
+        if self.pos == start_pos {
+            let token = self.bump();
+            if self.errors.last().is_none_or(|v| v.span != token.span) {
+                self.error(
+                    format!("unexpected `{}`", self.text(token.span)),
+                    token.span,
+                );
+            }
+            return self.stmt(StmtKind::Error, start);
+        }
+
+        self.stmt(stmt_kind, start)
+    }
+
+    /// The body of an `if`, `while` and friends. A stray `}` ends the body rather than
+    /// being swallowed.
+    fn parse_body(&mut self) -> Stmt {
+        if self.at_block_end() {
+            let start = self.cur_start();
+            self.error("expected a statement", self.cur_span());
+            return self.stmt(StmtKind::Error, start);
+        }
+        self.parse_stmt()
+    }
+
+    /// An assignment, or an expression used as a statement.
+    fn parse_simple_stmt(&mut self) -> StmtKind {
+        let target = self.parse_prefix();
+
+        let op = match self.peek() {
+            Some(TokenKind::Equal) | Some(TokenKind::ColonEqual) => AssignOp::Assign,
+            Some(TokenKind::PlusEqual) => AssignOp::Add,
+            Some(TokenKind::MinusEqual) => AssignOp::Subtract,
+            Some(TokenKind::StarEqual) => AssignOp::Multiply,
+            Some(TokenKind::SlashEqual) => AssignOp::Divide,
+            Some(TokenKind::PercentEqual) => AssignOp::Modulo,
+            Some(TokenKind::AmpEqual) => AssignOp::BitAnd,
+            Some(TokenKind::PipeEqual) => AssignOp::BitOr,
+            Some(TokenKind::CaretEqual) => AssignOp::BitXor,
+            Some(TokenKind::QuestionQuestionEqual) => AssignOp::NullCoalesce,
+            _ => {
+                let expr = self.parse_infix(target, 0);
+                return StmtKind::Expr(expr);
+            }
+        };
+        self.bump();
+
+        let value = self.parse_expr();
+        StmtKind::Assign { target, op, value }
+    }
+
+    fn parse_var_decls(&mut self) -> Vec<VarDecl> {
+        let mut decls = vec![];
+        while let Some(name) = self.ident("a variable name") {
+            let value = (self.eat(TokenKind::Equal) || self.eat(TokenKind::ColonEqual))
+                .then(|| self.parse_expr());
+            decls.push(VarDecl { name, value });
+
+            if self.eat(TokenKind::Comma) == false {
+                break;
+            }
+        }
+        self.eat(TokenKind::Semicolon);
+
+        decls
+    }
+
+    fn parse_function(&mut self) -> FunctionDecl {
+        let start = self.cur_start();
+        self.bump();
+
+        let name = if self.at(TokenKind::Identifier) {
+            self.ident("a function name")
+        } else {
+            None
+        };
+
+        let params_start = self.cur_start();
+        let mut params = vec![];
+        if self.expect(TokenKind::LeftParen, "`(`") {
+            while self.at(TokenKind::RightParen) == false && self.at_end() == false {
+                let Some(name) = self.ident("a parameter name") else {
+                    break;
+                };
+                let default = self.eat(TokenKind::Equal).then(|| self.parse_expr());
+                params.push(Param { name, default });
+
+                if self.eat(TokenKind::Comma) == false {
+                    break;
+                }
+            }
+            self.expect(TokenKind::RightParen, "`)`");
+        }
+        let params_span = Span::new(params_start, self.prev_end().max(params_start));
+
+        let inherits = self.eat(TokenKind::Colon).then(|| {
+            let parent = self.parse_prefix();
+            if matches!(parent.kind, ExprKind::Call { .. }) == false {
+                self.error("expected a parent constructor call", parent.span);
+            }
+            Box::new(parent)
+        });
+        let constructor = self.eat(TokenKind::Constructor);
+        let body = self.parse_block();
+
+        FunctionDecl {
+            name,
+            params,
+            params_span,
+            inherits,
+            constructor,
+            body,
+            span: Span::new(start, self.prev_end()),
+        }
+    }
+
+    fn parse_enum(&mut self) -> EnumDecl {
+        let start = self.cur_start();
+        self.bump();
+        let name = self.ident("an enum name").unwrap_or(Ident {
+            name: String::new(),
+            span: Span::new(start, start),
+        });
+
+        let mut members = vec![];
+        if self.expect(TokenKind::LeftBrace, "`{`") {
+            while self.at(TokenKind::RightBrace) == false && self.at_end() == false {
+                let Some(name) = self.ident("an enum member") else {
+                    break;
+                };
+                let value = self.eat(TokenKind::Equal).then(|| self.parse_expr());
+                members.push(EnumMember { name, value });
+
+                if self.eat(TokenKind::Comma) == false {
+                    break;
+                }
+            }
+            self.expect(TokenKind::RightBrace, "`}`");
+        }
+
+        EnumDecl { name, members }
+    }
+
+    fn parse_macro(&mut self, token: Token) -> Option<MacroDecl> {
+        let txt = self.text(token.span);
+        let after_directive = "#macro".len();
+
+        // finds the next identifier-shaped word after `from`, as an absolute span.
+        let word = |from: usize| -> Option<Span> {
+            let rest = &txt[from..];
+            let skip = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+            let len = rest[skip..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len() - skip);
+            (len > 0).then(|| {
+                Span::new(
+                    token.span.start + from + skip,
+                    token.span.start + from + skip + len,
+                )
+            })
+        };
+
+        let Some(first) = word(after_directive) else {
+            self.error("expected a macro name", token.span);
+            return None;
+        };
+
+        let mut local_end = first.end - token.span.start;
+        let (config, name) = if txt[local_end..].starts_with(':') {
+            match word(local_end + 1) {
+                Some(name) => {
+                    local_end = name.end - token.span.start;
+                    let config = Ident {
+                        name: self.text(first).to_string(),
+                        span: first,
+                    };
+                    (Some(config), name)
+                }
+                None => {
+                    self.error("expected a macro name", token.span);
+                    return None;
+                }
+            }
+        } else {
+            (None, first)
+        };
+
+        let body_txt = &txt[local_end..];
+        let leading = body_txt.len() - body_txt.trim_start().len();
+        let body_span = Span::new(token.span.start + local_end + leading, token.span.end);
+
+        Some(MacroDecl {
+            config,
+            name: Ident {
+                name: self.text(name).to_string(),
+                span: name,
+            },
+            body: self.text(body_span).to_string(),
+            body_span,
+        })
+    }
+
+    fn parse_for(&mut self) -> StmtKind {
+        self.bump();
+        let has_paren = self.expect(TokenKind::LeftParen, "`(`");
+
+        let init = if self.eat(TokenKind::Semicolon) {
+            None
+        } else {
+            let stmt = self.parse_stmt();
+            Some(Box::new(stmt))
+        };
+
+        let condition = if self.at(TokenKind::Semicolon) {
+            None
+        } else {
+            Some(self.parse_expr())
+        };
+        self.expect(TokenKind::Semicolon, "`;`");
+
+        let step = if self.at(TokenKind::RightParen) {
+            None
+        } else {
+            let start = self.cur_start();
+            let kind = self.parse_simple_stmt();
+            Some(Box::new(self.stmt(kind, start)))
+        };
+        if has_paren {
+            self.expect(TokenKind::RightParen, "`)`");
+        }
+
+        let body = Box::new(self.parse_body());
+        StmtKind::For {
+            init,
+            condition,
+            step,
+            body,
+        }
+    }
+
+    fn parse_switch(&mut self) -> StmtKind {
+        self.bump();
+        let value = self.parse_expr();
+
+        let mut cases = vec![];
+        if self.expect(TokenKind::LeftBrace, "`{`") {
+            while self.at_block_end() == false {
+                let start = self.cur_start();
+                let value = if self.eat(TokenKind::Case) {
+                    Some(self.parse_expr())
+                } else if self.eat(TokenKind::Default) {
+                    None
+                } else {
+                    self.error("expected `case` or `default`", self.cur_span());
+                    self.bump();
+                    continue;
+                };
+                self.expect(TokenKind::Colon, "`:`");
+
+                let mut body = vec![];
+                while self.at_block_end() == false
+                    && self.at(TokenKind::Case) == false
+                    && self.at(TokenKind::Default) == false
+                {
+                    body.push(self.parse_stmt());
+                }
+
+                cases.push(SwitchCase {
+                    value,
+                    body,
+                    span: Span::new(start, self.prev_end()),
+                });
+            }
+            self.expect(TokenKind::RightBrace, "`}`");
+        }
+
+        StmtKind::Switch { value, cases }
+    }
+
+    fn parse_try(&mut self) -> StmtKind {
+        self.bump();
+        let body = self.parse_block();
+
+        let catch = self.eat(TokenKind::Catch).then(|| {
+            let has_paren = self.eat(TokenKind::LeftParen);
+            let binding = if self.at(TokenKind::Identifier) {
+                self.ident("a binding")
+            } else {
+                None
+            };
+            if has_paren {
+                self.expect(TokenKind::RightParen, "`)`");
+            }
+            CatchClause {
+                binding,
+                body: self.parse_block(),
+            }
+        });
+        let finally = self.eat(TokenKind::Finally).then(|| self.parse_block());
+
+        StmtKind::Try {
+            body,
+            catch,
+            finally,
+        }
+    }
+
+    // --- expressions ---
+
+    fn can_start_expr(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(
+                TokenKind::Identifier
+                    | TokenKind::Number
+                    | TokenKind::String
+                    | TokenKind::TemplateString
+                    | TokenKind::LeftParen
+                    | TokenKind::LeftBracket
+                    | TokenKind::LeftBrace
+                    | TokenKind::Function
+                    | TokenKind::New
+                    | TokenKind::Minus
+                    | TokenKind::Plus
+                    | TokenKind::Bang
+                    | TokenKind::Not
+                    | TokenKind::Tilde
+                    | TokenKind::PlusPlus
+                    | TokenKind::MinusMinus
+            )
+        )
+    }
+
+    pub fn parse_expr(&mut self) -> Expr {
+        let lhs = self.parse_prefix();
+        self.parse_infix(lhs, 0)
+    }
+
+    fn parse_infix(&mut self, mut lhs: Expr, min_bp: u8) -> Expr {
+        while let Some(kind) = self.peek() {
+            if kind == TokenKind::Question {
+                const TERNARY_BP: u8 = 1;
+                if TERNARY_BP < min_bp {
+                    break;
+                }
+                self.bump();
+                let then_branch = self.parse_expr();
+                self.expect(TokenKind::Colon, "`:`");
+                let else_branch = {
+                    let lhs = self.parse_prefix();
+                    self.parse_infix(lhs, TERNARY_BP)
+                };
+
+                let start = lhs.span.start;
+                lhs = self.expr(
+                    ExprKind::Ternary {
+                        condition: Box::new(lhs),
+                        then_branch: Box::new(then_branch),
+                        else_branch: Box::new(else_branch),
+                    },
+                    start,
+                );
+                continue;
+            }
+
+            let Some((op, left_bp, right_bp)) = binary_op(kind) else {
+                break;
+            };
+            if left_bp < min_bp {
+                break;
+            }
+            self.bump();
+
+            let rhs = {
+                let lhs = self.parse_prefix();
+                self.parse_infix(lhs, right_bp)
+            };
+            let start = lhs.span.start;
+            lhs = self.expr(
+                ExprKind::Binary {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+                start,
+            );
+        }
+
+        lhs
+    }
+
+    fn parse_prefix(&mut self) -> Expr {
+        let start = self.cur_start();
+
+        let unary = match self.peek() {
+            Some(TokenKind::Minus) => Some(UnaryOp::Negate),
+            Some(TokenKind::Plus) => Some(UnaryOp::Plus),
+            Some(TokenKind::Bang) | Some(TokenKind::Not) => Some(UnaryOp::Not),
+            Some(TokenKind::Tilde) => Some(UnaryOp::BitNot),
+            Some(TokenKind::PlusPlus) => Some(UnaryOp::PreIncrement),
+            Some(TokenKind::MinusMinus) => Some(UnaryOp::PreDecrement),
+            _ => None,
+        };
+        if let Some(op) = unary {
+            self.bump();
+            let expr = Box::new(self.parse_prefix());
+            return self.expr(ExprKind::Unary { op, expr }, start);
+        }
+
+        if self.eat(TokenKind::New) {
+            let callee = self.parse_postfix();
+            return self.expr(ExprKind::New(Box::new(callee)), start);
+        }
+
+        self.parse_postfix()
+    }
+
+    fn parse_postfix(&mut self) -> Expr {
+        let start = self.cur_start();
+        let mut expr = self.parse_primary();
+
+        loop {
+            match self.peek() {
+                Some(TokenKind::LeftParen) => {
+                    let args_start = self.cur_start();
+                    self.bump();
+                    let mut args = vec![];
+                    while self.at(TokenKind::RightParen) == false && self.at_end() == false {
+                        if self.at(TokenKind::Comma) {
+                            // `f(, x)` is legal enough that we shouldn't throw the call away.
+                            args.push(self.expr(ExprKind::Error, self.cur_start()));
+                        } else {
+                            let before = self.pos;
+                            args.push(self.parse_expr());
+                            if self.pos == before {
+                                break;
+                            }
+                        }
+                        if self.eat(TokenKind::Comma) == false {
+                            break;
+                        }
+                    }
+                    self.expect(TokenKind::RightParen, "`)`");
+
+                    expr = self.expr(
+                        ExprKind::Call {
+                            callee: Box::new(expr),
+                            args,
+                            args_span: Span::new(args_start, self.prev_end()),
+                        },
+                        start,
+                    );
+                }
+                Some(TokenKind::Dot) => {
+                    let dot = self.bump();
+                    let field = if self.at(TokenKind::Identifier) {
+                        self.ident("a field name").unwrap()
+                    } else {
+                        // keep the node around so that completion after `a.` knows the lhs.
+                        self.error("expected a field name", self.cur_span());
+                        Ident {
+                            name: String::new(),
+                            span: Span::new(dot.span.end, dot.span.end),
+                        }
+                    };
+                    expr = self.expr(
+                        ExprKind::Field {
+                            expr: Box::new(expr),
+                            field,
+                        },
+                        start,
+                    );
+                }
+                Some(
+                    kind @ (TokenKind::LeftBracket
+                    | TokenKind::ListAccessor
+                    | TokenKind::MapAccessor
+                    | TokenKind::GridAccessor
+                    | TokenKind::ArrayAccessor
+                    | TokenKind::StructAccessor),
+                ) => {
+                    self.bump();
+                    let accessor = match kind {
+                        TokenKind::ListAccessor => Accessor::List,
+                        TokenKind::MapAccessor => Accessor::Map,
+                        TokenKind::GridAccessor => Accessor::Grid,
+                        TokenKind::ArrayAccessor => Accessor::ArrayReference,
+                        TokenKind::StructAccessor => Accessor::Struct,
+                        _ => Accessor::Array,
+                    };
+
+                    let mut indices = vec![];
+                    while self.at(TokenKind::RightBracket) == false && self.at_end() == false {
+                        let before = self.pos;
+                        indices.push(self.parse_expr());
+                        if self.pos == before || self.eat(TokenKind::Comma) == false {
+                            break;
+                        }
+                    }
+                    self.expect(TokenKind::RightBracket, "`]`");
+
+                    expr = self.expr(
+                        ExprKind::Index {
+                            expr: Box::new(expr),
+                            accessor,
+                            indices,
+                        },
+                        start,
+                    );
+                }
+                Some(TokenKind::PlusPlus) | Some(TokenKind::MinusMinus) => {
+                    let op = if self.bump().kind == TokenKind::PlusPlus {
+                        PostfixOp::Increment
+                    } else {
+                        PostfixOp::Decrement
+                    };
+                    expr = self.expr(
+                        ExprKind::Postfix {
+                            op,
+                            expr: Box::new(expr),
+                        },
+                        start,
+                    );
+                }
+                _ => break,
+            }
+        }
+
+        expr
+    }
+
+    fn parse_primary(&mut self) -> Expr {
+        let start = self.cur_start();
+        let Some(kind) = self.peek() else {
+            self.error("expected an expression", self.cur_span());
+            return self.expr(ExprKind::Error, start);
+        };
+
+        let expr_kind = match kind {
+            TokenKind::Identifier => ExprKind::Ident(self.ident("an identifier").unwrap()),
+            TokenKind::Number => {
+                let token = self.bump();
+                ExprKind::Number(self.text(token.span).to_string())
+            }
+            TokenKind::String => {
+                let token = self.bump();
+                ExprKind::String(self.text(token.span).to_string())
+            }
+            TokenKind::TemplateString => {
+                let token = self.bump();
+                ExprKind::TemplateString(self.parse_template(token))
+            }
+            TokenKind::LeftParen => {
+                self.bump();
+                let inner = self.parse_expr();
+                self.expect(TokenKind::RightParen, "`)`");
+                ExprKind::Grouping(Box::new(inner))
+            }
+            TokenKind::LeftBracket => {
+                self.bump();
+                let mut values = vec![];
+                while self.at(TokenKind::RightBracket) == false && self.at_end() == false {
+                    let before = self.pos;
+                    values.push(self.parse_expr());
+                    if self.pos == before || self.eat(TokenKind::Comma) == false {
+                        break;
+                    }
+                }
+                self.expect(TokenKind::RightBracket, "`]`");
+                ExprKind::Array(values)
+            }
+            TokenKind::LeftBrace => {
+                self.bump();
+                let mut fields = vec![];
+                while self.at(TokenKind::RightBrace) == false && self.at_end() == false {
+                    let name = if self.at(TokenKind::String) {
+                        let token = self.bump();
+                        Ident {
+                            name: self.text(token.span).trim_matches('"').to_string(),
+                            span: token.span,
+                        }
+                    } else {
+                        match self.ident("a field name") {
+                            Some(v) => v,
+                            None => break,
+                        }
+                    };
+                    let value = self.eat(TokenKind::Colon).then(|| self.parse_expr());
+                    fields.push(StructField { name, value });
+
+                    if self.eat(TokenKind::Comma) == false {
+                        break;
+                    }
+                }
+                self.expect(TokenKind::RightBrace, "`}`");
+                ExprKind::Struct(fields)
+            }
+            TokenKind::Function => ExprKind::Function(Box::new(self.parse_function())),
+            _ => {
+                self.error("expected an expression", self.cur_span());
+                return self.expr(ExprKind::Error, start);
+            }
+        };
+
+        self.expr(expr_kind, start)
+    }
+
+    /// Parses the `{expr}` holes in a template string token.
+    fn parse_template(&mut self, token: Token) -> Vec<Expr> {
+        let mut output = vec![];
+
+        // skip the `$"`
+        let body_start = token.span.start + 2;
+        let mut hole_start = None;
+        let mut depth = 0usize;
+        let mut in_string = false;
+
+        let mut iter = self.txt[body_start..token.span.end].char_indices();
+        while let Some((i, chr)) = iter.next() {
+            let i = body_start + i;
+            match chr {
+                '\\' if depth == 0 || in_string => {
+                    iter.next();
+                }
+                '"' if depth > 0 => in_string = !in_string,
+                '{' if in_string == false => {
+                    if depth == 0 {
+                        hole_start = Some(i + 1);
+                    }
+                    depth += 1;
+                }
+                '}' if in_string == false && depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if let Some(hole_start) = hole_start.take() {
+                            output.extend(self.parse_hole(hole_start, i));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        output
+    }
+
+    fn parse_hole(&mut self, start: usize, end: usize) -> Option<Expr> {
+        let tokens: Vec<_> = Lexer::new(&self.txt[..end], start)
+            .filter(|v| v.kind.is_trivia() == false)
+            .collect();
+        if tokens.is_empty() {
+            return None;
+        }
+
+        let mut parser = Parser::new(self.txt, &tokens);
+        let expr = parser.parse_expr();
+        if let Some(extra) = tokens.get(parser.pos) {
+            parser.error("unexpected token in template string", extra.span);
+        }
+        self.errors.extend(parser.errors);
+
+        Some(expr)
+    }
+}
+
+/// Returns the operator and its left and right binding power.
+fn binary_op(kind: TokenKind) -> Option<(BinaryOp, u8, u8)> {
+    let output = match kind {
+        TokenKind::QuestionQuestion => (BinaryOp::NullCoalesce, 3, 4),
+        TokenKind::PipePipe | TokenKind::Or => (BinaryOp::Or, 5, 6),
+        TokenKind::CaretCaret | TokenKind::Xor => (BinaryOp::Xor, 7, 8),
+        TokenKind::AmpAmp | TokenKind::And => (BinaryOp::And, 9, 10),
+        // a bare `=` in an expression is a comparison in GML.
+        TokenKind::EqualEqual | TokenKind::Equal => (BinaryOp::Equal, 11, 12),
+        TokenKind::BangEqual => (BinaryOp::NotEqual, 11, 12),
+        TokenKind::Less => (BinaryOp::Less, 11, 12),
+        TokenKind::LessEqual => (BinaryOp::LessEqual, 11, 12),
+        TokenKind::Greater => (BinaryOp::Greater, 11, 12),
+        TokenKind::GreaterEqual => (BinaryOp::GreaterEqual, 11, 12),
+        TokenKind::Pipe => (BinaryOp::BitOr, 13, 14),
+        TokenKind::Caret => (BinaryOp::BitXor, 15, 16),
+        TokenKind::Amp => (BinaryOp::BitAnd, 17, 18),
+        TokenKind::LessLess => (BinaryOp::ShiftLeft, 19, 20),
+        TokenKind::GreaterGreater => (BinaryOp::ShiftRight, 19, 20),
+        TokenKind::Plus => (BinaryOp::Add, 21, 22),
+        TokenKind::Minus => (BinaryOp::Subtract, 21, 22),
+        TokenKind::Star => (BinaryOp::Multiply, 23, 24),
+        TokenKind::Slash => (BinaryOp::Divide, 23, 24),
+        TokenKind::Percent | TokenKind::Mod => (BinaryOp::Modulo, 23, 24),
+        TokenKind::Div => (BinaryOp::IntDivide, 23, 24),
+        _ => return None,
+    };
+
+    Some(output)
+}
@@ -0,0 +1,224 @@
+use super::*;
+
+/// A name which is local to a function or event, as opposed to one on an instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Local<'a> {
+    pub ident: &'a Ident,
+    pub kind: LocalKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LocalKind {
+    Var,
+    Param,
+    Static,
+    Catch,
+}
+
+impl SyntaxTree {
+    /// Every local which can be seen from the cursor, in the order they were declared. A local
+    /// can be seen anywhere in its function once it's been declared, even after the block it was
+    /// declared in has ended, but a function can't see the locals of the code around it.
+    pub fn locals_at(&self, offset: usize) -> Vec<Local<'_>> {
+        let declared = self
+            .function_locals(self.function_at(offset))
+            .into_iter()
+            .filter(|v| v.ident.span.start < offset);
+
+        // declaring a local again doesn't make a new one, so just keep the latest declaration
+        let mut output: Vec<Local<'_>> = vec![];
+        for local in declared.rev() {
+            if output.iter().any(|v| v.ident.name == local.ident.name) == false {
+                output.push(local);
+            }
+        }
+        output.reverse();
+
+        output
+    }
+
+    /// Finds the local which a name refers to at the cursor.
+    pub fn local(&self, name: &str, offset: usize) -> Option<Local<'_>> {
+        self.locals_at(offset)
+            .into_iter()
+            .find(|v| v.ident.name == name)
+    }
+
+    /// The innermost function around the cursor, if it's in one at all.
+    pub fn function_at(&self, offset: usize) -> Option<&FunctionDecl> {
+        let mut finder = FunctionFinder {
+            offset,
+            function: None,
+        };
+        for stmt in &self.stmts {
+            finder.visit_stmt(stmt);
+        }
+
+        finder.function
+    }
+
+    /// Every local declared by a function, or by the top level of the document when `func` is
+    /// `None`, leaving out those of the functions nested inside of it.
+    pub fn function_locals<'a>(&'a self, func: Option<&'a FunctionDecl>) -> Vec<Local<'a>> {
+        let mut finder = FunctionLocalFinder { locals: vec![] };
+        match func {
+            Some(func) => {
+                for param in &func.params {
+                    finder.push(&param.name, LocalKind::Param);
+                }
+                walk_function(&mut finder, func);
+            }
+            None => {
+                for stmt in &self.stmts {
+                    finder.visit_stmt(stmt);
+                }
+            }
+        }
+
+        finder.locals
+    }
+
+    /// Every declaration of the local `name` in the function around the cursor. Unlike `local`,
+    /// this ignores blocks, since a `var` can still be used after the block it's declared in has
+    /// ended, right up until the end of its function.
+    pub fn function_local(&self, name: &str, offset: usize) -> Vec<Local<'_>> {
+        self.function_locals(self.function_at(offset))
+            .into_iter()
+            .filter(|v| v.ident.name == name)
+            .collect()
+    }
+}
+
+struct FunctionFinder<'a> {
+    offset: usize,
+    function: Option<&'a FunctionDecl>,
+}
+
+impl<'a> Visitor<'a> for FunctionFinder<'a> {
+    fn visit_function(&mut self, func: &'a FunctionDecl) {
+        if func.span.start <= self.offset && self.offset < func.span.end {
+            self.function = Some(func);
+            walk_function(self, func);
+        }
+    }
+}
+
+struct FunctionLocalFinder<'a> {
+    locals: Vec<Local<'a>>,
+}
+
+impl<'a> FunctionLocalFinder<'a> {
+    fn push(&mut self, ident: &'a Ident, kind: LocalKind) {
+        self.locals.push(Local { ident, kind });
+    }
+}
+
+impl<'a> Visitor<'a> for FunctionLocalFinder<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match &stmt.kind {
+            StmtKind::Var(decls) => {
+                for decl in decls {
+                    self.push(&decl.name, LocalKind::Var);
+                }
+            }
+            StmtKind::Static(decls) => {
+                for decl in decls {
+                    self.push(&decl.name, LocalKind::Static);
+                }
+            }
+            StmtKind::Try {
+                catch: Some(catch), ..
+            } => {
+                if let Some(binding) = &catch.binding {
+                    self.push(binding, LocalKind::Catch);
+                }
+            }
+            _ => {}
+        }
+
+        walk_stmt(self, stmt);
+    }
+
+    // a nested function has locals of its own
+    fn visit_function(&mut self, _: &'a FunctionDecl) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locals() {
+        let txt = r#"
+var a = 1;
+if (a) {
+    var b = 2;
+    /*1*/
+}
+/*2*/
+for (var i = 0; i < 3; i++) { /*3*/ }
+function f(x, y = a) {
+    static calls = 0;
+    var a = x;
+    /*4*/
+}
+try {} catch (e) { /*5*/ }
+switch (a) {
+    case 1: var c = 3; /*6*/ break;
+}
+var d = function(z) { /*7*/ };
+"#;
+        let tree = SyntaxTree::parse(txt);
+        let locals = |marker: &str| -> Vec<_> {
+            let offset = txt.find(marker).unwrap();
+            tree.locals_at(offset)
+                .into_iter()
+                .map(|v| v.ident.name.as_str())
+                .collect()
+        };
+
+        assert_eq!(locals("/*1*/"), vec!["a", "b"]);
+        // a `var` outlives the block it's declared in
+        assert_eq!(locals("/*2*/"), vec!["a", "b"]);
+        assert_eq!(locals("/*3*/"), vec!["a", "b", "i"]);
+        assert_eq!(locals("/*4*/"), vec!["x", "y", "calls", "a"]);
+        assert_eq!(locals("/*5*/"), vec!["a", "b", "i", "e"]);
+        assert_eq!(locals("/*6*/"), vec!["a", "b", "i", "e", "c"]);
+        assert_eq!(locals("/*7*/"), vec!["z"]);
+
+        let offset = txt.find("/*4*/").unwrap();
+        let local = tree.local("a", offset).unwrap();
+        assert_eq!(local.kind, LocalKind::Var);
+        assert_eq!(tree.range(txt, local.ident.span).start.line, 10);
+        assert_eq!(tree.local("x", offset).unwrap().kind, LocalKind::Param);
+    }
+
+    #[test]
+    fn function_locals() {
+        let txt = r#"
+if (c) { var a = 1; } else { var a = 2; }
+show_debug_message(a); /*1*/
+function f(x) {
+    for (var i = 0; i < x; i++) { var a = i; }
+    var g = function(y) { var b = y; };
+    /*2*/
+}
+"#;
+        let tree = SyntaxTree::parse(txt);
+        let names = |marker: &str| -> Vec<_> {
+            let offset = txt.find(marker).unwrap();
+            tree.function_locals(tree.function_at(offset))
+                .into_iter()
+                .map(|v| v.ident.name.as_str())
+                .collect()
+        };
+
+        assert_eq!(names("/*1*/"), vec!["a", "a"]);
+        assert_eq!(names("/*2*/"), vec!["x", "i", "a", "g"]);
+        let offset = txt.find("/*1*/").unwrap();
+        let declarations = tree.function_local("a", offset);
+        assert_eq!(declarations.len(), 2);
+        assert_eq!(tree.local("a", offset), Some(declarations[1]));
+        assert_eq!(tree.function_local("b", txt.find("/*2*/").unwrap()), vec![]);
+    }
+}
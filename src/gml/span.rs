@@ -0,0 +1,109 @@
+use crate::lsp::{Position, Range};
+
+/// A half-open byte range into a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+/// Maps byte offsets to line/column positions and back. Columns are counted in chars, the same
+/// way [Position::get_idx] counts them.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(txt: &str) -> Self {
+        let mut line_starts = vec![0];
+        for (i, b) in txt.bytes().enumerate() {
+            if b == b'\n' {
+                line_starts.push(i + 1);
+            }
+        }
+
+        Self { line_starts }
+    }
+
+    pub fn position(&self, txt: &str, offset: usize) -> Position {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let line_start = self.line_starts[line];
+        let column = txt
+            .get(line_start..offset)
+            .map(|v| v.chars().count())
+            .unwrap_or_default();
+
+        Position { line, column }
+    }
+
+    pub fn offset(&self, txt: &str, position: Position) -> Option<usize> {
+        let line_start = *self.line_starts.get(position.line)?;
+        let line_end = self
+            .line_starts
+            .get(position.line + 1)
+            .copied()
+            .unwrap_or(txt.len());
+
+        let line = &txt[line_start..line_end];
+        let mut chars_to_go = position.column;
+        for (i, chr) in line.char_indices() {
+            if chars_to_go == 0 {
+                return Some(line_start + i);
+            }
+            if chr == '\n' {
+                return None;
+            }
+            chars_to_go -= 1;
+        }
+
+        (chars_to_go == 0).then_some(line_end)
+    }
+
+    pub fn range(&self, txt: &str, span: Span) -> Range {
+        Range {
+            start: self.position(txt, span.start),
+            end: self.position(txt, span.end),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_index_round_trip() {
+        let txt = "var a = 1;\n\tb = \"ü\";\r\nc";
+        let index = LineIndex::new(txt);
+        for (offset, _) in txt.char_indices() {
+            let position = index.position(txt, offset);
+            assert_eq!(position.get_idx(txt), Some(offset));
+            assert_eq!(index.offset(txt, position), Some(offset));
+        }
+
+        assert_eq!(
+            index.position(txt, txt.len()),
+            Position { line: 2, column: 1 }
+        );
+        assert_eq!(
+            index.offset(
+                txt,
+                Position {
+                    line: 0,
+                    column: 40
+                }
+            ),
+            None
+        );
+    }
+}
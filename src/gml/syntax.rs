@@ -0,0 +1,268 @@
+use super::Span;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    Expr(Expr),
+    Assign {
+        target: Expr,
+        op: AssignOp,
+        value: Expr,
+    },
+    Var(Vec<VarDecl>),
+    Static(Vec<VarDecl>),
+    GlobalVar(Vec<Ident>),
+    Function(FunctionDecl),
+    Enum(EnumDecl),
+    Macro(MacroDecl),
+    Block(Block),
+    If {
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+    },
+    While {
+        condition: Expr,
+        body: Box<Stmt>,
+    },
+    DoUntil {
+        body: Box<Stmt>,
+        condition: Expr,
+    },
+    Repeat {
+        count: Expr,
+        body: Box<Stmt>,
+    },
+    For {
+        init: Option<Box<Stmt>>,
+        condition: Option<Expr>,
+        step: Option<Box<Stmt>>,
+        body: Box<Stmt>,
+    },
+    With {
+        target: Expr,
+        body: Box<Stmt>,
+    },
+    Switch {
+        value: Expr,
+        cases: Vec<SwitchCase>,
+    },
+    Try {
+        body: Block,
+        catch: Option<CatchClause>,
+        finally: Option<Block>,
+    },
+    Return(Option<Expr>),
+    Exit,
+    Break,
+    Continue,
+    Throw(Expr),
+    Delete(Expr),
+    Empty,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssignOp {
+    Assign,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    BitAnd,
+    BitOr,
+    BitXor,
+    NullCoalesce,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarDecl {
+    pub name: Ident,
+    pub value: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: Ident,
+    pub default: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDecl {
+    pub name: Option<Ident>,
+    pub params: Vec<Param>,
+    /// The span of the parameter list, including the parentheses.
+    pub params_span: Span,
+    /// The parent constructor call in `function Child() : Parent() constructor`.
+    pub inherits: Option<Box<Expr>>,
+    pub constructor: bool,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDecl {
+    pub name: Ident,
+    pub members: Vec<EnumMember>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumMember {
+    pub name: Ident,
+    pub value: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroDecl {
+    /// The build configuration in `#macro Config:NAME value`.
+    pub config: Option<Ident>,
+    pub name: Ident,
+    /// The raw replacement text, with line continuations left in.
+    pub body: String,
+    pub body_span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwitchCase {
+    /// `None` for the `default` case.
+    pub value: Option<Expr>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatchClause {
+    pub binding: Option<Ident>,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Ident(Ident),
+    Number(String),
+    String(String),
+    /// The embedded expressions of a `$"..."` string.
+    TemplateString(Vec<Expr>),
+    Array(Vec<Expr>),
+    Struct(Vec<StructField>),
+    Function(Box<FunctionDecl>),
+    Grouping(Box<Expr>),
+    Unary {
+        op: UnaryOp,
+        expr: Box<Expr>,
+    },
+    Postfix {
+        op: PostfixOp,
+        expr: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Ternary {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+        /// The span of the argument list, including the parentheses. If the call is missing
+        /// its `)`, this runs to the last token we could make sense of.
+        args_span: Span,
+    },
+    New(Box<Expr>),
+    Field {
+        expr: Box<Expr>,
+        field: Ident,
+    },
+    Index {
+        expr: Box<Expr>,
+        accessor: Accessor,
+        indices: Vec<Expr>,
+    },
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructField {
+    pub name: Ident,
+    /// `None` for the shorthand `{ a }`.
+    pub value: Option<Expr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Accessor {
+    Array,
+    List,
+    Map,
+    Grid,
+    ArrayReference,
+    Struct,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+    Negate,
+    Plus,
+    Not,
+    BitNot,
+    PreIncrement,
+    PreDecrement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PostfixOp {
+    Increment,
+    Decrement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    IntDivide,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    And,
+    Or,
+    Xor,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    NullCoalesce,
+}
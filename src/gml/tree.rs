@@ -0,0 +1,462 @@
+use super::*;
+use crate::lsp::{Position, Range};
+
+/// The parsed form of a single `.gml` document. The tree keeps every token, including comments
+/// and whitespace, alongside the statements, so that questions about the cursor can be answered
+/// without going back to the raw text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxTree {
+    pub tokens: Vec<Token>,
+    pub stmts: Vec<Stmt>,
+    pub errors: Vec<ParseError>,
+    pub line_index: LineIndex,
+    /// How many of the `errors` each top level statement produced, so that an edit can replace
+    /// the errors of just the statements it reparses.
+    pub(super) error_counts: Vec<usize>,
+}
+
+/// A call expression which the cursor is inside the argument list of.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CallSite<'a> {
+    pub callee: &'a Expr,
+    pub args: &'a [Expr],
+    pub args_span: Span,
+    pub active_param: usize,
+    /// True for `new Name(...)`.
+    pub is_new: bool,
+}
+
+impl<'a> CallSite<'a> {
+    /// The name of the function being called. For method calls like `a.b()`, this is `b`.
+    pub fn name(&self) -> Option<&'a str> {
+        match &self.callee.kind {
+            ExprKind::Ident(ident) => Some(&ident.name),
+            ExprKind::Field { field, .. } => Some(&field.name),
+            _ => None,
+        }
+    }
+}
+
+impl SyntaxTree {
+    pub fn parse(txt: &str) -> Self {
+        let tokens = lex(txt);
+        let significant: Vec<_> = tokens
+            .iter()
+            .filter(|v| v.kind.is_trivia() == false)
+            .copied()
+            .collect();
+
+        let mut parser = Parser::new(txt, &significant);
+        let mut stmts = vec![];
+        let mut error_counts = vec![];
+        while parser.at_end() == false {
+            parse_top_level(&mut parser, &mut stmts, &mut error_counts);
+        }
+
+        Self {
+            tokens,
+            stmts,
+            errors: parser.finish(),
+            line_index: LineIndex::new(txt),
+            error_counts,
+        }
+    }
+
+    pub fn offset(&self, txt: &str, position: Position) -> Option<usize> {
+        self.line_index.offset(txt, position)
+    }
+
+    pub fn range(&self, txt: &str, span: Span) -> Range {
+        self.line_index.range(txt, span)
+    }
+
+    /// Finds the token under the cursor. When the cursor sits between two tokens, a word on the
+    /// left wins, since that's the word which is being typed.
+    pub fn token_at(&self, offset: usize) -> Option<Token> {
+        let idx = self.tokens.partition_point(|v| v.span.end < offset);
+        let token = *self.tokens.get(idx)?;
+
+        if token.span.end == offset && token.span.start != offset {
+            let left_wins = token.kind.is_word()
+                || matches!(token.kind, TokenKind::LineComment | TokenKind::DocComment);
+            if left_wins == false {
+                if let Some(next) = self.tokens.get(idx + 1) {
+                    return Some(*next);
+                }
+            }
+        }
+
+        Some(token)
+    }
+
+    /// Returns the part of the word under the cursor which comes before it. This is `Some("")`
+    /// when the cursor is somewhere a word could start, and `None` in comments and strings.
+    pub fn word_before<'t>(&self, txt: &'t str, offset: usize) -> Option<&'t str> {
+        let Some(token) = self.token_at(offset) else {
+            return Some("");
+        };
+
+        match token.kind {
+            kind if kind.is_comment() => None,
+            TokenKind::String | TokenKind::TemplateString | TokenKind::Macro
+                if token.span.start < offset =>
+            {
+                None
+            }
+            kind if kind.is_word() && token.span.start < offset => {
+                txt.get(token.span.start..offset)
+            }
+            _ => Some(""),
+        }
+    }
+
+    /// Returns the whole word under the cursor, if there is one.
+    pub fn word_at<'t>(&self, txt: &'t str, offset: usize) -> Option<(&'t str, Span)> {
+        self.token_at(offset)
+            .filter(|v| v.kind.is_word())
+            .map(|v| (&txt[v.span.start..v.span.end], v.span))
+    }
+
+    /// For a cursor in or just after the `field` of `receiver.field`, returns `receiver`. Only
+    /// plain identifiers count as receivers.
+    pub fn receiver_before<'t>(&self, txt: &'t str, offset: usize) -> Option<&'t str> {
+        let end = self.tokens.partition_point(|v| v.span.end <= offset);
+        let mut tokens = self.tokens[..end]
+            .iter()
+            .rev()
+            .filter(|v| v.kind.is_trivia() == false)
+            .peekable();
+
+        // skip over the part of the field which has been typed so far
+        tokens.next_if(|v| v.kind.is_word() && v.span.end == offset);
+        tokens.next_if(|v| v.kind == TokenKind::Dot)?;
+
+        tokens
+            .next()
+            .filter(|v| v.kind == TokenKind::Identifier)
+            .map(|v| &txt[v.span.start..v.span.end])
+    }
+
+    /// Finds the innermost call whose argument list the cursor is in.
+    pub fn call_at(&self, offset: usize) -> Option<CallSite<'_>> {
+        let mut finder = CallFinder {
+            tree: self,
+            offset,
+            pending_new: None,
+            best: None,
+        };
+        for stmt in &self.stmts {
+            finder.visit_stmt(stmt);
+        }
+
+        finder.best
+    }
+
+    /// The targets of every `with` whose body the cursor is in, outermost first.
+    pub fn with_targets_at(&self, offset: usize) -> Vec<&Expr> {
+        let mut finder = WithFinder {
+            offset,
+            targets: vec![],
+        };
+        for stmt in &self.stmts {
+            finder.visit_stmt(stmt);
+        }
+
+        finder.targets
+    }
+
+    fn significant_tokens_in(&self, span: Span) -> impl Iterator<Item = &Token> {
+        let start = self.tokens.partition_point(|v| v.span.start < span.start);
+        self.tokens[start..]
+            .iter()
+            .take_while(move |v| v.span.end <= span.end)
+            .filter(|v| v.kind.is_trivia() == false)
+    }
+
+    fn is_call_closed(&self, args_span: Span) -> bool {
+        let mut depth = 0isize;
+        for token in self.significant_tokens_in(args_span) {
+            match token.kind {
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen => depth -= 1,
+                _ => {}
+            }
+        }
+        depth == 0
+    }
+
+    /// Counts the top level commas between the `(` of a call and the cursor.
+    fn active_param(&self, args_span: Span, offset: usize) -> usize {
+        let mut depth = 0usize;
+        let mut commas = 0;
+        for token in self
+            .significant_tokens_in(Span::new(args_span.start, offset))
+            .skip(1)
+        {
+            match token.kind {
+                TokenKind::LeftParen
+                | TokenKind::LeftBracket
+                | TokenKind::LeftBrace
+                | TokenKind::ListAccessor
+                | TokenKind::MapAccessor
+                | TokenKind::GridAccessor
+                | TokenKind::ArrayAccessor
+                | TokenKind::StructAccessor => depth += 1,
+                TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace => {
+                    depth = depth.saturating_sub(1)
+                }
+                TokenKind::Comma if depth == 0 => commas += 1,
+                _ => {}
+            }
+        }
+        commas
+    }
+}
+
+pub(super) fn parse_top_level(
+    parser: &mut Parser<'_>,
+    stmts: &mut Vec<Stmt>,
+    error_counts: &mut Vec<usize>,
+) {
+    let before = parser.error_count();
+    stmts.push(parser.parse_top_level());
+    error_counts.push(parser.error_count() - before);
+}
+
+struct CallFinder<'a> {
+    tree: &'a SyntaxTree,
+    offset: usize,
+    pending_new: Option<Span>,
+    best: Option<CallSite<'a>>,
+}
+
+impl<'a> CallFinder<'a> {
+    fn contains(&self, args_span: Span) -> bool {
+        if self.offset <= args_span.start {
+            return false;
+        }
+        if self.tree.is_call_closed(args_span) {
+            return self.offset < args_span.end;
+        }
+
+        // an unclosed call runs until the next thing we could parse.
+        let next_start = self
+            .tree
+            .tokens
+            .iter()
+            .skip_while(|v| v.span.start < args_span.end)
+            .find(|v| v.kind.is_trivia() == false)
+            .map(|v| v.span.start)
+            .unwrap_or(usize::MAX);
+
+        self.offset <= next_start
+    }
+}
+
+impl<'a> Visitor<'a> for CallFinder<'a> {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match &expr.kind {
+            ExprKind::New(inner) => {
+                self.pending_new = Some(inner.span);
+            }
+            ExprKind::Call {
+                callee,
+                args,
+                args_span,
+            } => {
+                let is_new = self.pending_new == Some(expr.span);
+                let deeper = self
+                    .best
+                    .is_none_or(|v| v.args_span.start < args_span.start);
+
+                if deeper && self.contains(*args_span) {
+                    self.best = Some(CallSite {
+                        callee,
+                        args,
+                        args_span: *args_span,
+                        active_param: self.tree.active_param(*args_span, self.offset),
+                        is_new,
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        walk_expr(self, expr);
+    }
+}
+
+struct WithFinder<'a> {
+    offset: usize,
+    targets: Vec<&'a Expr>,
+}
+
+impl<'a> Visitor<'a> for WithFinder<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        // nothing outside of this statement can contain the cursor
+        if (stmt.span.start..=stmt.span.end).contains(&self.offset) == false {
+            return;
+        }
+
+        if let StmtKind::With { target, body } = &stmt.kind {
+            if body.span.start < self.offset && self.offset <= body.span.end {
+                self.targets.push(target);
+            }
+        }
+
+        walk_stmt(self, stmt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_ok(txt: &str) -> SyntaxTree {
+        let tree = SyntaxTree::parse(txt);
+        assert!(tree.errors.is_empty(), "{:#?}", tree.errors);
+        tree
+    }
+
+    #[test]
+    fn statements() {
+        let tree = parse_ok(
+            r#"
+/// @function scr_spawn(x, y)
+function scr_spawn(_x, _y = 10) {
+    var inst = instance_create_depth(_x, _y, 0, obj_enemy), count
+    static calls = 0;
+    calls++
+    if inst.hp <= 0 then exit else { inst.hp -= 1 }
+    with (inst) { speed = 2; }
+    for (var i = 0; i < 10; i++) { arr[@ i] = ds_map[? "key"]; }
+    switch (state) { case EState.Idle: break; default: state = undefined }
+    try { throw "oops" } catch (e) { show_debug_message($"{e.message} at {calls + 1}") }
+    return new Vector(_x, _y)
+}
+
+enum EState { Idle, Walk = 5, Run }
+#macro Debug:SPEED 5
+globalvar money;
+global.lives = 3
+var f = function(a) constructor { self.a = a }
+"#,
+        );
+
+        let kinds: Vec<_> = tree
+            .stmts
+            .iter()
+            .map(|v| std::mem::discriminant(&v.kind))
+            .collect();
+        assert_eq!(kinds.len(), 6);
+
+        let StmtKind::Function(func) = &tree.stmts[0].kind else {
+            panic!("expected a function");
+        };
+        assert_eq!(func.name.as_ref().unwrap().name, "scr_spawn");
+        assert_eq!(func.params.len(), 2);
+        assert!(func.params[1].default.is_some());
+        assert_eq!(func.body.stmts.len(), 9);
+
+        let StmtKind::Enum(decl) = &tree.stmts[1].kind else {
+            panic!("expected an enum");
+        };
+        assert_eq!(decl.members.len(), 3);
+
+        let StmtKind::Macro(decl) = &tree.stmts[2].kind else {
+            panic!("expected a macro");
+        };
+        assert_eq!(decl.config.as_ref().unwrap().name, "Debug");
+        assert_eq!(decl.name.name, "SPEED");
+        assert_eq!(decl.body, "5");
+    }
+
+    #[test]
+    fn error_recovery() {
+        let txt = "var a = ;\nfoo(1, 2\nb = 3;\n} c = 4;";
+        let tree = SyntaxTree::parse(txt);
+        assert!(tree.errors.is_empty() == false);
+
+        let last = tree.stmts.last().unwrap();
+        assert!(matches!(last.kind, StmtKind::Assign { .. }));
+        assert_eq!(&txt[last.span.start..last.span.end], "c = 4;");
+    }
+
+    #[test]
+    fn spans() {
+        let txt = "a = 1;\n\tfoo(bar);";
+        let tree = parse_ok(txt);
+
+        let stmt = &tree.stmts[1];
+        assert_eq!(&txt[stmt.span.start..stmt.span.end], "foo(bar);");
+        assert_eq!(
+            tree.range(txt, stmt.span),
+            Range {
+                start: Position { line: 1, column: 1 },
+                end: Position {
+                    line: 1,
+                    column: 10
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn words() {
+        let txt = "draw_sp // comment\nx = \"str\"";
+        let tree = SyntaxTree::parse(txt);
+
+        assert_eq!(tree.word_before(txt, 7), Some("draw_sp"));
+        assert_eq!(tree.word_before(txt, 3), Some("dra"));
+        assert_eq!(tree.word_at(txt, 3).unwrap().0, "draw_sp");
+        assert_eq!(tree.word_before(txt, 12), None);
+        assert_eq!(tree.word_before(txt, txt.len() - 2), None);
+        assert_eq!(tree.word_before(txt, 21), Some(""));
+
+        let txt = "a = EState.Wa + EState . b.c";
+        let tree = SyntaxTree::parse(txt);
+        assert_eq!(tree.receiver_before(txt, 11), Some("EState"));
+        assert_eq!(tree.receiver_before(txt, 13), Some("EState"));
+        assert_eq!(tree.receiver_before(txt, 9), None);
+        assert_eq!(tree.receiver_before(txt, 25), Some("EState"));
+        assert_eq!(tree.receiver_before(txt, txt.len()), Some("b"));
+    }
+
+    #[test]
+    fn calls() {
+        let txt = "a = foo(1, bar(2, 3), [4, 5]";
+        let tree = SyntaxTree::parse(txt);
+
+        let site = tree.call_at(txt.find("3").unwrap()).unwrap();
+        assert_eq!(site.name(), Some("bar"));
+        assert_eq!(site.active_param, 1);
+
+        let site = tree.call_at(txt.find("5").unwrap()).unwrap();
+        assert_eq!(site.name(), Some("foo"));
+        assert_eq!(site.active_param, 2);
+
+        let site = tree.call_at(txt.len()).unwrap();
+        assert_eq!(site.name(), Some("foo"));
+
+        let txt = "v = new Vector(1, 2);";
+        let tree = parse_ok(txt);
+        let site = tree.call_at(txt.find("2").unwrap()).unwrap();
+        assert!(site.is_new);
+        assert!(tree.call_at(txt.len()).is_none());
+    }
+
+    #[test]
+    fn with_targets() {
+        let txt = "with (a) { with (other) { x = 1; } y = 2; }";
+        let tree = parse_ok(txt);
+        let targets = |offset| -> Vec<_> {
+            tree.with_targets_at(offset)
+                .into_iter()
+                .map(|v| &txt[v.span.start..v.span.end])
+                .collect()
+        };
+        assert_eq!(targets(txt.find("x").unwrap()), vec!["(a)", "(other)"]);
+        assert_eq!(targets(txt.find("y").unwrap()), vec!["(a)"]);
+        assert!(targets(txt.find("(").unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+use super::*;
+
+/// Walks a syntax tree. Override the methods you care about, and call the matching `walk_*`
+/// function to keep descending.
+pub trait Visitor<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_block(&mut self, block: &'a Block) {
+        walk_block(self, block);
+    }
+
+    fn visit_function(&mut self, func: &'a FunctionDecl) {
+        walk_function(self, func);
+    }
+}
+
+pub fn walk_block<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, block: &'a Block) {
+    for stmt in &block.stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_function<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, func: &'a FunctionDecl) {
+    for param in &func.params {
+        if let Some(default) = &param.default {
+            visitor.visit_expr(default);
+        }
+    }
+    if let Some(inherits) = &func.inherits {
+        visitor.visit_expr(inherits);
+    }
+    visitor.visit_block(&func.body);
+}
+
+pub fn walk_stmt<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, stmt: &'a Stmt) {
+    match &stmt.kind {
+        StmtKind::Expr(expr) | StmtKind::Throw(expr) | StmtKind::Delete(expr) => {
+            visitor.visit_expr(expr)
+        }
+        StmtKind::Assign { target, value, .. } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        StmtKind::Var(decls) | StmtKind::Static(decls) => {
+            for decl in decls {
+                if let Some(value) = &decl.value {
+                    visitor.visit_expr(value);
+                }
+            }
+        }
+        StmtKind::Function(func) => visitor.visit_function(func),
+        StmtKind::Enum(decl) => {
+            for member in &decl.members {
+                if let Some(value) = &member.value {
+                    visitor.visit_expr(value);
+                }
+            }
+        }
+        StmtKind::Block(block) => visitor.visit_block(block),
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_stmt(else_branch);
+            }
+        }
+        StmtKind::While { condition, body } | StmtKind::DoUntil { body, condition } => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(body);
+        }
+        StmtKind::Repeat { count, body } => {
+            visitor.visit_expr(count);
+            visitor.visit_stmt(body);
+        }
+        StmtKind::With { target, body } => {
+            visitor.visit_expr(target);
+            visitor.visit_stmt(body);
+        }
+        StmtKind::For {
+            init,
+            condition,
+            step,
+            body,
+        } => {
+            if let Some(init) = init {
+                visitor.visit_stmt(init);
+            }
+            if let Some(condition) = condition {
+                visitor.visit_expr(condition);
+            }
+            if let Some(step) = step {
+                visitor.visit_stmt(step);
+            }
+            visitor.visit_stmt(body);
+        }
+        StmtKind::Switch { value, cases } => {
+            visitor.visit_expr(value);
+            for case in cases {
+                if let Some(value) = &case.value {
+                    visitor.visit_expr(value);
+                }
+                for stmt in &case.body {
+                    visitor.visit_stmt(stmt);
+                }
+            }
+        }
+        StmtKind::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            visitor.visit_block(body);
+            if let Some(catch) = catch {
+                visitor.visit_block(&catch.body);
+            }
+            if let Some(finally) = finally {
+                visitor.visit_block(finally);
+            }
+        }
+        StmtKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        StmtKind::GlobalVar(_)
+        | StmtKind::Macro(_)
+        | StmtKind::Exit
+        | StmtKind::Break
+        | StmtKind::Continue
+        | StmtKind::Empty
+        | StmtKind::Error => {}
+    }
+}
+
+pub fn walk_expr<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, expr: &'a Expr) {
+    match &expr.kind {
+        ExprKind::Ident(_) | ExprKind::Number(_) | ExprKind::String(_) | ExprKind::Error => {}
+        ExprKind::TemplateString(exprs) | ExprKind::Array(exprs) => {
+            for expr in exprs {
+                visitor.visit_expr(expr);
+            }
+        }
+        ExprKind::Struct(fields) => {
+            for field in fields {
+                if let Some(value) = &field.value {
+                    visitor.visit_expr(value);
+                }
+            }
+        }
+        ExprKind::Function(func) => visitor.visit_function(func),
+        ExprKind::Grouping(expr)
+        | ExprKind::Unary { expr, .. }
+        | ExprKind::Postfix { expr, .. }
+        | ExprKind::New(expr)
+        | ExprKind::Field { expr, .. } => visitor.visit_expr(expr),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprKind::Ternary {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_expr(then_branch);
+            visitor.visit_expr(else_branch);
+        }
+        ExprKind::Call { callee, args, .. } => {
+            visitor.visit_expr(callee);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Index { expr, indices, .. } => {
+            visitor.visit_expr(expr);
+            for index in indices {
+                visitor.visit_expr(index);
+            }
+        }
+    }
+}
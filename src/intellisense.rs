@@ -1,4 +1,12 @@
+pub mod code_actions;
 pub mod completion;
+pub mod definition;
+pub mod diagnostics;
 pub mod hover;
+pub mod instance;
+pub mod project;
+pub mod references;
+pub mod rename;
 pub mod signature;
 mod utils;
+pub mod workspace_symbols;
@@ -0,0 +1,29 @@
+use std::collections::HashMap;
+
+use lsp_types::{CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, Url, WorkspaceEdit};
+
+use super::diagnostics::QuickFix;
+
+/// Turns the quick fixes our diagnostics carry into code actions. The client sends back the
+/// diagnostics which are under the cursor, so there's nothing to recompute.
+pub fn code_actions(uri: &Url, diagnostics: &[Diagnostic]) -> Vec<CodeActionOrCommand> {
+    diagnostics
+        .iter()
+        .filter(|v| v.source.as_deref() == Some("gm-code"))
+        .filter_map(|diagnostic| {
+            let fix: QuickFix = serde_json::from_value(diagnostic.data.clone()?).ok()?;
+
+            Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title: fix.title,
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), fix.edits)])),
+                    ..WorkspaceEdit::default()
+                }),
+                is_preferred: Some(true),
+                ..CodeAction::default()
+            }))
+        })
+        .collect()
+}
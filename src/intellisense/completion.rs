@@ -1,53 +1,63 @@
 use super::utils;
 use itertools::Itertools;
-use lsp_types::{CompletionItem, CompletionItemKind, CompletionList, MarkedString, MarkupContent};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionItemTag, CompletionList, MarkedString,
+    MarkupContent,
+};
 use yy_boss::YypBoss;
 
 use super::utils::StdCompletionKind;
+use crate::gml::SyntaxTree;
+use crate::services::{InstanceVariable, SymbolIndex, SymbolKind};
 
 pub fn initial_completion(
     input_str: &str,
     gm_manual: &gm_doc::Program,
     yy_boss: &YypBoss,
+    symbols: &SymbolIndex,
+    instance_variables: &[&InstanceVariable],
+    constant_classes: &[&str],
 ) -> CompletionList {
     let mut output = vec![];
 
-    // check for functions:
-    for func in gm_manual.functions.values() {
-        if func.name.contains(input_str) {
-            output.push(CompletionItem {
-                label: func.name.clone(),
-                kind: Some(CompletionItemKind::FUNCTION),
-                data: serde_json::to_value(StdCompletionKind::Function).ok(),
-                ..CompletionItem::default()
-            })
-        }
-    }
+    // check for the variables of the instance we're in:
+    output.extend(instance_variable_items(
+        input_str,
+        instance_variables,
+        gm_manual,
+    ));
 
-    // check for variables:
-    for variable in gm_manual.variables.values() {
-        if variable.name.contains(input_str) {
-            output.push(CompletionItem {
-                label: variable.name.clone(),
-                kind: Some(CompletionItemKind::VARIABLE),
-                data: serde_json::to_value(StdCompletionKind::Variable).ok(),
+    output.extend(builtin_completion(input_str, gm_manual, constant_classes));
 
-                ..CompletionItem::default()
-            })
+    // check for things declared in the project:
+    for (name, declarations) in symbols.iter() {
+        if name.contains(input_str) == false {
+            continue;
         }
-    }
 
-    // check for constants:
-    for constant in gm_manual.constants.values() {
-        if constant.name.contains(input_str) {
-            output.push(CompletionItem {
-                label: constant.name.clone(),
-                kind: Some(CompletionItemKind::VALUE),
-                data: serde_json::to_value(StdCompletionKind::Constant).ok(),
+        let (kind, data) = match &declarations[0].kind {
+            SymbolKind::Function(func) if func.constructor => {
+                (CompletionItemKind::CLASS, StdCompletionKind::UserFunction)
+            }
+            SymbolKind::Function(_) => (
+                CompletionItemKind::FUNCTION,
+                StdCompletionKind::UserFunction,
+            ),
+            SymbolKind::Enum(_) => (CompletionItemKind::ENUM, StdCompletionKind::Enum),
+            SymbolKind::GlobalVariable => (
+                CompletionItemKind::VARIABLE,
+                StdCompletionKind::GlobalVariable,
+            ),
+            SymbolKind::Macro(_) => (CompletionItemKind::CONSTANT, StdCompletionKind::Macro),
+        };
 
-                ..CompletionItem::default()
-            })
-        }
+        output.push(CompletionItem {
+            label: name.to_string(),
+            kind: Some(kind),
+            data: serde_json::to_value(data).ok(),
+
+            ..CompletionItem::default()
+        })
     }
 
     // Check for object names:
@@ -93,34 +103,338 @@ pub fn initial_completion(
     }
 }
 
+/// Completes the functions, variables and constants from the manual.
+fn builtin_completion(
+    input_str: &str,
+    gm_manual: &gm_doc::Program,
+    constant_classes: &[&str],
+) -> Vec<CompletionItem> {
+    let mut output = vec![];
+
+    // check for functions:
+    for func in gm_manual.functions.values() {
+        if func.name.contains(input_str) {
+            output.push(CompletionItem {
+                label: func.name.clone(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                data: serde_json::to_value(StdCompletionKind::Function).ok(),
+
+                ..builtin_item(&func.name, func.deprecated)
+            })
+        }
+    }
+
+    // check for variables:
+    for variable in gm_manual.variables.values() {
+        if variable.name.contains(input_str) {
+            output.push(CompletionItem {
+                label: variable.name.clone(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                data: serde_json::to_value(StdCompletionKind::Variable).ok(),
+
+                ..builtin_item(&variable.name, variable.deprecated)
+            })
+        }
+    }
+
+    // check for constants. when the argument wants a class of constants, only offer those, and
+    // ahead of everything but the locals:
+    for constant in gm_manual.constants.values() {
+        if constant.name.contains(input_str) == false {
+            continue;
+        }
+        let wanted = constant
+            .class
+            .as_deref()
+            .is_some_and(|v| constant_classes.contains(&v));
+        if constant_classes.is_empty() == false && wanted == false {
+            continue;
+        }
+
+        let mut item = CompletionItem {
+            label: constant.name.clone(),
+            kind: Some(CompletionItemKind::VALUE),
+            data: serde_json::to_value(StdCompletionKind::Constant).ok(),
+
+            ..builtin_item(&constant.name, constant.deprecated)
+        };
+        if wanted && constant.deprecated == false {
+            item.sort_text = Some(format!("1{}", constant.name));
+        }
+        output.push(item);
+    }
+
+    output
+}
+
+/// The classes of constants which the argument under the cursor wants, like `Color` when the
+/// parameter is a `Constant.Color`.
+pub fn constant_classes<'a>(
+    tree: &SyntaxTree,
+    offset: usize,
+    gm_manual: &'a gm_doc::Program,
+) -> Vec<&'a str> {
+    let Some(call) = tree.call_at(offset).filter(|v| v.is_new == false) else {
+        return vec![];
+    };
+    let Some(func) = call.name().and_then(|v| gm_manual.functions.get(v)) else {
+        return vec![];
+    };
+
+    // past the end of a variadic function, we're still on its last parameter
+    let params = &func.parameters;
+    let param = params
+        .get(call.active_param)
+        .or_else(|| params.last().filter(|v| v.name.contains("...")));
+
+    param
+        .map(|v| {
+            utils::gm_type_union(&v.gm_type)
+                .into_iter()
+                .filter_map(|v| v.strip_prefix("Constant."))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Deprecated builtins are struck out, and sort after everything else.
+fn builtin_item(name: &str, deprecated: bool) -> CompletionItem {
+    if deprecated == false {
+        return CompletionItem::default();
+    }
+
+    CompletionItem {
+        tags: Some(vec![CompletionItemTag::DEPRECATED]),
+        sort_text: Some(format!("~{}", name)),
+        ..CompletionItem::default()
+    }
+}
+
+/// Completes the locals which can be seen from the cursor. These sort ahead of everything else.
+pub fn local_completion(
+    input_str: &str,
+    txt: &str,
+    tree: &SyntaxTree,
+    offset: usize,
+) -> Vec<CompletionItem> {
+    tree.locals_at(offset)
+        .iter()
+        .filter(|v| v.ident.name.contains(input_str))
+        .map(|local| {
+            let docs = utils::local_docs_data(txt, tree, local);
+            CompletionItem {
+                label: local.ident.name.clone(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                detail: Some(docs.detail),
+                documentation: Some(documentation(docs.description)),
+                sort_text: Some(format!("0{}", local.ident.name)),
+
+                ..CompletionItem::default()
+            }
+        })
+        .collect()
+}
+
+/// Completes the members of `receiver` after a `receiver.`, if `receiver` is an enum. Nothing
+/// else makes sense there, so this replaces the usual completion.
+pub fn member_completion(
+    receiver: &str,
+    input_str: &str,
+    symbols: &SymbolIndex,
+) -> Option<CompletionList> {
+    let decl = symbols.get(receiver).iter().find_map(|v| match &v.kind {
+        SymbolKind::Enum(decl) => Some(decl),
+        _ => None,
+    })?;
+
+    let items = decl
+        .members
+        .iter()
+        .filter(|v| v.name.contains(input_str))
+        .map(|member| CompletionItem {
+            label: member.name.clone(),
+            kind: Some(CompletionItemKind::ENUM_MEMBER),
+            detail: Some(utils::enum_member_detail(receiver, member)),
+
+            ..CompletionItem::default()
+        })
+        .collect();
+
+    Some(CompletionList {
+        is_incomplete: false,
+        items,
+    })
+}
+
+/// Completes the variables of an instance after `self.`, `other.` or `obj_name.`.
+pub fn instance_member_completion(
+    input_str: &str,
+    instance_variables: &[&InstanceVariable],
+    gm_manual: &gm_doc::Program,
+) -> CompletionList {
+    CompletionList {
+        is_incomplete: false,
+        items: instance_variable_items(input_str, instance_variables, gm_manual),
+    }
+}
+
+fn instance_variable_items(
+    input_str: &str,
+    instance_variables: &[&InstanceVariable],
+    gm_manual: &gm_doc::Program,
+) -> Vec<CompletionItem> {
+    instance_variables
+        .iter()
+        .filter(|v| v.name.contains(input_str))
+        // builtin variables get their own, better documented, completions
+        .filter(|v| gm_manual.variables.contains_key(&v.name) == false)
+        .map(|variable| {
+            let docs = utils::instance_variable_docs_data(variable);
+            CompletionItem {
+                label: variable.name.clone(),
+                kind: Some(CompletionItemKind::FIELD),
+                detail: Some(docs.detail),
+                documentation: Some(documentation(docs.description)),
+
+                ..CompletionItem::default()
+            }
+        })
+        .collect()
+}
+
 pub fn resolve_completion(
     mut completion: CompletionItem,
     gm_manual: &gm_doc::Program,
     yy_boss: &YypBoss,
+    symbols: &SymbolIndex,
 ) -> CompletionItem {
     if let Some(data) = completion.data.clone() {
         if let Ok(v) = serde_json::from_value(data) {
             if let Some(output) =
-                utils::detailed_docs_data(&completion.label, &[v], gm_manual, yy_boss)
+                utils::detailed_docs_data(&completion.label, &[v], gm_manual, yy_boss, symbols)
             {
                 completion.detail = Some(output.detail);
-                let documentation = output
-                    .description
-                    .into_iter()
-                    .map(|v| match v {
-                        MarkedString::String(v) => v,
-                        MarkedString::LanguageString(l) => format!("```\n{}\n```", l.value),
-                    })
-                    .join("\n");
-
-                completion.documentation =
-                    Some(lsp_types::Documentation::MarkupContent(MarkupContent {
-                        kind: lsp_types::MarkupKind::Markdown,
-                        value: documentation,
-                    }));
+                completion.documentation = Some(documentation(output.description));
             }
         }
     }
 
     completion
 }
+
+fn documentation(description: Vec<MarkedString>) -> lsp_types::Documentation {
+    let documentation = description
+        .into_iter()
+        .map(|v| match v {
+            MarkedString::String(v) => v,
+            MarkedString::LanguageString(l) => format!("```\n{}\n```", l.value),
+        })
+        .join("\n");
+
+    lsp_types::Documentation::MarkupContent(MarkupContent {
+        kind: lsp_types::MarkupKind::Markdown,
+        value: documentation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intellisense::diagnostics::tests::manual;
+
+    #[test]
+    fn deprecated_builtins() {
+        let mut gm_manual = manual(&[("draw_text", &[]), ("draw_text_color", &[])]);
+        gm_manual
+            .functions
+            .get_mut("draw_text_color")
+            .unwrap()
+            .deprecated = true;
+
+        let items = builtin_completion("draw_text", &gm_manual, &[]);
+        let item = |label: &str| items.iter().find(|v| v.label == label).unwrap();
+
+        assert_eq!(item("draw_text").tags, None);
+        assert_eq!(item("draw_text").sort_text, None);
+        assert_eq!(
+            item("draw_text_color").tags,
+            Some(vec![CompletionItemTag::DEPRECATED])
+        );
+        assert_eq!(
+            item("draw_text_color").sort_text.as_deref(),
+            Some("~draw_text_color")
+        );
+    }
+
+    #[test]
+    fn wanted_constant_classes() {
+        let mut gm_manual = manual(&[
+            ("draw_set_colour", &[("col", "Constant.Color", false)]),
+            (
+                "draw_points",
+                &[("x", "Real", false), ("...colours", "Constant.Color", true)],
+            ),
+            ("draw_set_alpha", &[("alpha", "Real", false)]),
+            (
+                "keyboard_check",
+                &[("key", "Constant.VirtualKey,Real", false)],
+            ),
+        ]);
+        for (name, class) in [
+            ("c_red", "Color"),
+            ("c_blue", "Color"),
+            ("fa_left", "HAlign"),
+            ("vk_left", "VirtualKey"),
+        ] {
+            gm_manual.constants.insert(
+                name.to_string(),
+                gm_doc::Constant {
+                    name: name.to_string(),
+                    description: String::new(),
+                    returns: String::new(),
+                    class: Some(class.to_string()),
+                    deprecated: false,
+                    link: None,
+                },
+            );
+        }
+        let classes = |txt: &str| -> Vec<&str> {
+            constant_classes(&SyntaxTree::parse(txt), txt.len(), &gm_manual)
+        };
+
+        assert_eq!(classes("draw_set_colour("), vec!["Color"]);
+        assert_eq!(classes("draw_points(0, c_red, c_blue, "), vec!["Color"]);
+        assert!(classes("draw_points(").is_empty());
+        assert!(classes("draw_set_alpha(").is_empty());
+        assert!(classes("draw_set_alpha(0, ").is_empty());
+        assert_eq!(classes("keyboard_check("), vec!["VirtualKey"]);
+
+        let items = builtin_completion("", &gm_manual, &classes("keyboard_check("));
+        let constants: Vec<_> = items
+            .iter()
+            .filter(|v| v.kind == Some(CompletionItemKind::VALUE))
+            .map(|v| v.label.as_str())
+            .collect();
+        assert_eq!(constants, vec!["vk_left"]);
+
+        let items = builtin_completion("", &gm_manual, &["Color"]);
+        let constants: Vec<_> = items
+            .iter()
+            .filter(|v| v.kind == Some(CompletionItemKind::VALUE))
+            .map(|v| (v.label.as_str(), v.sort_text.as_deref()))
+            .collect();
+        assert_eq!(
+            constants,
+            vec![("c_blue", Some("1c_blue")), ("c_red", Some("1c_red"))]
+        );
+
+        let items = builtin_completion("", &gm_manual, &[]);
+        assert_eq!(
+            items
+                .iter()
+                .filter(|v| v.kind == Some(CompletionItemKind::VALUE))
+                .count(),
+            4
+        );
+    }
+}
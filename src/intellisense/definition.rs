@@ -0,0 +1,253 @@
+use std::path::PathBuf;
+
+use lsp_types::{GotoDefinitionResponse, Location, Range};
+use url::Url;
+
+use super::{instance::InstanceContext, project::Project};
+use crate::gml::{Local, SyntaxTree};
+use crate::services::{
+    EventInfo, InstanceVariable, ObjectInfo, Symbol, SymbolIndex, SymbolKind, VariableOrigin,
+};
+
+/// Finds where the word at the cursor is declared, resolving it the same way as hover does:
+/// members first, then locals, instance variables, the project's own declarations and assets.
+/// `event` is the object and event the document is the code of, if it's an event.
+pub fn definition_at(
+    project: &Project<'_>,
+    uri: &Url,
+    event: Option<(&ObjectInfo, &EventInfo)>,
+    offset: usize,
+) -> Option<GotoDefinitionResponse> {
+    let (txt, tree) = project.document(uri)?;
+    let (word, span) = tree.word_at(txt, offset)?;
+    let receiver = tree.receiver_before(txt, span.start);
+    let context = InstanceContext::at(tree, span.start, event, project.objects);
+
+    receiver
+        .and_then(|receiver| goto_member(receiver, word, project.symbols))
+        .or_else(|| {
+            let local = tree.local(word, span.end).filter(|_| receiver.is_none())?;
+            Some(goto_local(uri, txt, tree, &local))
+        })
+        .or_else(|| {
+            let object = match receiver {
+                Some(receiver) => context.resolve(receiver, project.objects)?,
+                None => context.self_object.clone()?,
+            };
+            let variable = project.objects.variable(&object, word)?;
+            if project.gm_manual.variables.contains_key(word) {
+                return None;
+            }
+
+            goto_instance_variable(variable)
+        })
+        .or_else(|| goto_definition(word, project.symbols))
+        .or_else(|| goto_asset(&project.asset_files(word)))
+}
+
+pub fn goto_definition(word: &str, symbols: &SymbolIndex) -> Option<GotoDefinitionResponse> {
+    response(symbols.get(word).iter().filter_map(location).collect())
+}
+
+/// Goes to the `member` of `receiver.member`, when `receiver` is an enum or `global`.
+pub fn goto_member(
+    receiver: &str,
+    member: &str,
+    symbols: &SymbolIndex,
+) -> Option<GotoDefinitionResponse> {
+    if receiver == "global" {
+        let globals = symbols
+            .get(member)
+            .iter()
+            .filter(|v| v.kind == SymbolKind::GlobalVariable)
+            .filter_map(location);
+        return response(globals.collect());
+    }
+
+    let members = symbols.get(receiver).iter().filter_map(|v| match &v.kind {
+        SymbolKind::Enum(decl) => {
+            let member = decl.member(member)?;
+            let uri = Url::from_file_path(&v.path).ok()?;
+            Some(Location::new(uri, member.range.into()))
+        }
+        _ => None,
+    });
+
+    response(members.collect())
+}
+
+pub fn goto_local(
+    uri: &Url,
+    txt: &str,
+    tree: &SyntaxTree,
+    local: &Local<'_>,
+) -> GotoDefinitionResponse {
+    let range = tree.range(txt, local.ident.span);
+    GotoDefinitionResponse::Scalar(Location::new(uri.clone(), range.into()))
+}
+
+/// Goes to the event which assigns to an instance variable. Object Variables are declared in the
+/// IDE rather than in code, so there's nowhere to go for them.
+pub fn goto_instance_variable(variable: &InstanceVariable) -> Option<GotoDefinitionResponse> {
+    match &variable.origin {
+        VariableOrigin::Event { path, range, .. } => {
+            let uri = Url::from_file_path(path).ok()?;
+            Some(GotoDefinitionResponse::Scalar(Location::new(
+                uri,
+                (*range).into(),
+            )))
+        }
+        VariableOrigin::Property { .. } => None,
+    }
+}
+
+/// Goes to the top of each of an asset's files.
+pub fn goto_asset(files: &[PathBuf]) -> Option<GotoDefinitionResponse> {
+    let locations = files
+        .iter()
+        .filter_map(|v| Url::from_file_path(v).ok())
+        .map(|uri| Location::new(uri, Range::default()));
+
+    response(locations.collect())
+}
+
+pub fn location(symbol: &Symbol) -> Option<Location> {
+    Url::from_file_path(&symbol.path)
+        .ok()
+        .map(|uri| Location::new(uri, symbol.range.into()))
+}
+
+fn response(locations: Vec<Location>) -> Option<GotoDefinitionResponse> {
+    (locations.is_empty() == false).then_some(GotoDefinitionResponse::Array(locations))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::intellisense::project::tests::{uri, TestProject};
+    use crate::services::{ResourceLookup, ResourceLookupData};
+
+    fn locations(response: Option<GotoDefinitionResponse>) -> Vec<(String, u32)> {
+        let locations = match response {
+            Some(GotoDefinitionResponse::Array(v)) => v,
+            Some(GotoDefinitionResponse::Scalar(v)) => vec![v],
+            _ => vec![],
+        };
+        locations
+            .into_iter()
+            .map(|v| (v.uri.path().to_string(), v.range.start.line))
+            .collect()
+    }
+
+    #[test]
+    fn definitions() {
+        let txt = "enum Dir {\n    left,\n    right,\n}\n#macro SPEED 4\nglobal.score = 0;\n\nfunction move() {}";
+        let mut symbols = SymbolIndex::default();
+        symbols.update_file(
+            Path::new("/project/scripts/scr_misc/scr_misc.gml"),
+            &ResourceLookup {
+                name: "scr_misc".to_string(),
+                data: ResourceLookupData::Script,
+            },
+            txt,
+            &SyntaxTree::parse(txt),
+        );
+        let path = "/project/scripts/scr_misc/scr_misc.gml".to_string();
+
+        assert_eq!(
+            locations(goto_definition("move", &symbols)),
+            vec![(path.clone(), 7)]
+        );
+        assert_eq!(
+            locations(goto_definition("SPEED", &symbols)),
+            vec![(path.clone(), 4)]
+        );
+        assert_eq!(
+            locations(goto_member("Dir", "right", &symbols)),
+            vec![(path.clone(), 2)]
+        );
+        assert_eq!(
+            locations(goto_member("global", "score", &symbols)),
+            vec![(path, 5)]
+        );
+        assert!(goto_member("global", "move", &symbols).is_none());
+        assert!(goto_member("Dir", "up", &symbols).is_none());
+
+        let files = [
+            PathBuf::from("/project/objects/obj_player/obj_player.yy"),
+            PathBuf::from("/project/objects/obj_player/Create_0.gml"),
+        ];
+        assert_eq!(
+            locations(goto_asset(&files)),
+            vec![
+                ("/project/objects/obj_player/obj_player.yy".to_string(), 0),
+                ("/project/objects/obj_player/Create_0.gml".to_string(), 0),
+            ]
+        );
+        assert!(goto_asset(&[]).is_none());
+    }
+
+    #[test]
+    fn definitions_at() {
+        let script = "enum Dir { left, right }\nglobal.score = 0;\nfunction spawn(count) {\n    var n = count;\n    return n;\n}";
+        let enemy =
+            "hp = 10;\nfacing = Dir.left;\nglobal.score += hp;\nspawn(1);\nvar o = obj_enemy;";
+        let player = "var d = obj_enemy.hp;";
+        let test_project = TestProject::new(
+            &[
+                ("scripts/scr_enemy/scr_enemy.gml", script),
+                ("objects/obj_enemy/Create_0.gml", enemy),
+                ("objects/obj_player/Create_0.gml", player),
+            ],
+            &[
+                ("scripts/scr_enemy/scr_enemy.yy", "{}"),
+                ("objects/obj_enemy/obj_enemy.yy", "{}"),
+                ("objects/obj_player/obj_player.yy", "{}"),
+            ],
+        );
+        let project = test_project.project();
+        let definition = |path: &str, offset: usize| {
+            let object = project.objects.get(path.split('/').nth(1).unwrap());
+            let event = object.and_then(|v| Some((v, v.events.first()?)));
+            locations(definition_at(&project, &uri(path), event, offset))
+        };
+        let scr = "scripts/scr_enemy/scr_enemy.gml";
+        let obj = "objects/obj_enemy/Create_0.gml";
+        let at = |path: &str, line: u32| (format!("/project/{}", path), line);
+
+        assert_eq!(
+            definition(obj, enemy.find("left").unwrap()),
+            vec![at(scr, 0)]
+        );
+        assert_eq!(
+            definition(obj, enemy.find("score").unwrap()),
+            vec![at(scr, 1)]
+        );
+        assert_eq!(
+            definition(obj, enemy.rfind("hp").unwrap()),
+            vec![at(obj, 0)]
+        );
+        assert_eq!(
+            definition(
+                "objects/obj_player/Create_0.gml",
+                player.find("hp").unwrap()
+            ),
+            vec![at(obj, 0)]
+        );
+        assert_eq!(
+            definition(scr, script.rfind('n').unwrap()),
+            vec![at(scr, 3)]
+        );
+        assert_eq!(
+            definition(obj, enemy.find("spawn").unwrap()),
+            vec![at(scr, 2)]
+        );
+        assert_eq!(
+            definition(obj, enemy.find("obj_enemy").unwrap()),
+            vec![at("objects/obj_enemy/obj_enemy.yy", 0), at(obj, 0)]
+        );
+        assert!(definition(obj, enemy.find("10").unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeSet;
+
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, TextEdit};
+use serde::{Deserialize, Serialize};
+use yy_typings::EventType;
+
+use crate::{
+    gml::*,
+    services::{ObjectIndex, ObjectInfo, SymbolIndex},
+};
+
+mod arguments;
+pub use arguments::*;
+
+mod deprecated;
+pub use deprecated::*;
+
+mod discarded;
+pub use discarded::*;
+
+mod event_inherited;
+pub use event_inherited::*;
+
+mod instance_only;
+pub use instance_only::*;
+
+mod read_only;
+pub use read_only::*;
+
+mod syntax;
+pub use syntax::*;
+
+mod undefined;
+pub use undefined::*;
+
+mod unreachable;
+pub use unreachable::*;
+
+mod unused;
+pub use unused::*;
+
+/// Everything a [Rule] might want to know about the document it's checking.
+pub struct DiagnosticContext<'a> {
+    pub txt: &'a str,
+    pub tree: &'a SyntaxTree,
+    /// The object and event the document is the code of, if it's an event.
+    pub event: Option<(&'a ObjectInfo, EventType)>,
+    pub objects: &'a ObjectIndex,
+    pub gm_manual: &'a gm_doc::Program,
+    pub symbols: &'a SymbolIndex,
+    /// The name of every asset in the project.
+    pub assets: &'a BTreeSet<String>,
+}
+
+impl DiagnosticContext<'_> {
+    pub fn diagnostic(
+        &self,
+        span: Span,
+        severity: DiagnosticSeverity,
+        message: String,
+    ) -> Diagnostic {
+        Diagnostic {
+            range: self.tree.range(self.txt, span).into(),
+            severity: Some(severity),
+            message,
+            ..Diagnostic::default()
+        }
+    }
+
+    /// Every call to a builtin function by name, which a local hasn't taken the name of.
+    pub fn builtin_calls(&self) -> Vec<BuiltinCall<'_>> {
+        let mut finder = BuiltinCallFinder {
+            context: self,
+            calls: vec![],
+        };
+        for stmt in &self.tree.stmts {
+            finder.visit_stmt(stmt);
+        }
+
+        finder.calls
+    }
+}
+
+pub struct BuiltinCall<'a> {
+    pub callee: &'a Expr,
+    pub args: &'a [Expr],
+    pub func: &'a gm_doc::Function,
+}
+
+struct BuiltinCallFinder<'a, 'b> {
+    context: &'b DiagnosticContext<'a>,
+    calls: Vec<BuiltinCall<'a>>,
+}
+
+impl<'a> Visitor<'a> for BuiltinCallFinder<'a, '_> {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if let ExprKind::Call { callee, args, .. } = &expr.kind {
+            if let ExprKind::Ident(ident) = &callee.kind {
+                let func = self.context.gm_manual.functions.get(&ident.name);
+                let local = self.context.tree.local(&ident.name, ident.span.start);
+                if let Some(func) = func.filter(|_| local.is_none()) {
+                    self.calls.push(BuiltinCall { callee, args, func });
+                }
+            }
+        }
+
+        walk_expr(self, expr);
+    }
+}
+
+/// An edit which fixes a diagnostic, sent along in its `data` so that we can offer it as a code
+/// action without checking the document again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuickFix {
+    pub title: String,
+    pub edits: Vec<TextEdit>,
+}
+
+/// A single check which diagnostics are made of.
+pub trait Rule {
+    /// A short name for the rule, which is sent as the code of its diagnostics.
+    fn name(&self) -> &'static str;
+
+    fn check(&self, context: &DiagnosticContext<'_>, output: &mut Vec<Diagnostic>);
+}
+
+/// Every [Rule] we run over a document.
+pub struct RuleRegistry {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Default for RuleRegistry {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+impl RuleRegistry {
+    /// Every rule, with `allowed_identifiers` as names which are made at runtime and so aren't
+    /// undefined.
+    pub fn new(allowed_identifiers: Vec<String>) -> Self {
+        let mut output = Self { rules: vec![] };
+        output.register(SyntaxErrors);
+        output.register(EventInherited);
+        output.register(BuiltinArguments);
+        output.register(Deprecated);
+        output.register(ReadOnly);
+        output.register(InstanceOnly);
+        output.register(UndefinedIdentifiers {
+            allowed: allowed_identifiers,
+        });
+        output.register(UnusedLocals);
+        output.register(Unreachable);
+        output.register(DiscardedResult);
+
+        output
+    }
+
+    pub fn register(&mut self, rule: impl Rule + 'static) {
+        self.rules.push(Box::new(rule));
+    }
+
+    /// Runs every rule over a document, in the order they appear in it.
+    pub fn run(&self, context: &DiagnosticContext<'_>) -> Vec<Diagnostic> {
+        let mut output = vec![];
+
+        for rule in &self.rules {
+            let start = output.len();
+            rule.check(context, &mut output);
+
+            for diagnostic in &mut output[start..] {
+                diagnostic.source = Some("gm-code".to_string());
+                diagnostic.code = Some(NumberOrString::String(rule.name().to_string()));
+            }
+        }
+        output.sort_by_key(|v| (v.range.start.line, v.range.start.character));
+
+        output
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Runs a single rule, so that tests don't see what the others have to say.
+    pub fn run_one(rule: impl Rule + 'static, context: &DiagnosticContext<'_>) -> Vec<Diagnostic> {
+        let mut registry = RuleRegistry { rules: vec![] };
+        registry.register(rule);
+
+        registry.run(context)
+    }
+
+    /// Runs a rule over some text which isn't part of any object, in a project with just an
+    /// `obj_player`.
+    pub fn check(
+        rule: impl Rule + 'static,
+        txt: &str,
+        gm_manual: &gm_doc::Program,
+    ) -> Vec<Diagnostic> {
+        let tree = SyntaxTree::parse(txt);
+        let mut objects = ObjectIndex::default();
+        objects.insert(ObjectInfo::new("obj_player".to_string(), []));
+        let context = DiagnosticContext {
+            txt,
+            tree: &tree,
+            event: None,
+            objects: &objects,
+            gm_manual,
+            symbols: &SymbolIndex::default(),
+            assets: &["obj_player".to_string()].into(),
+        };
+
+        run_one(rule, &context)
+    }
+
+    /// Parameters as `(name, type, optional)`.
+    pub type Params<'a> = &'a [(&'a str, &'a str, bool)];
+
+    /// A manual with just the given functions.
+    pub fn manual(functions: &[(&str, Params<'_>)]) -> gm_doc::Program {
+        let mut output = gm_doc::Program::default();
+        for (name, params) in functions {
+            let parameters = params
+                .iter()
+                .map(|(name, gm_type, optional)| gm_doc::Parameter {
+                    name: name.to_string(),
+                    description: String::new(),
+                    gm_type: gm_type.to_string(),
+                    optional: *optional,
+                })
+                .collect();
+
+            output.functions.insert(
+                name.to_string(),
+                gm_doc::Function {
+                    name: name.to_string(),
+                    parameters,
+                    description: String::new(),
+                    returns: "Undefined".to_string(),
+                    deprecated: false,
+                    pure: false,
+                    link: None,
+                },
+            );
+        }
+
+        output
+    }
+
+    /// A manual with just the given variables, as `(name, settable, instance)`.
+    pub fn manual_variables(variables: &[(&str, bool, bool)]) -> gm_doc::Program {
+        let mut output = gm_doc::Program::default();
+        for (name, set, instance) in variables {
+            output.variables.insert(
+                name.to_string(),
+                gm_doc::Field {
+                    name: name.to_string(),
+                    description: String::new(),
+                    deprecated: false,
+                    get: true,
+                    set: *set,
+                    instance: *instance,
+                    returns: "Real".to_string(),
+                    link: None,
+                },
+            );
+        }
+
+        output
+    }
+
+    #[test]
+    fn registry() {
+        let tree = SyntaxTree::parse("a = ;\nb = 1 +;");
+        let output = RuleRegistry::default().run(&DiagnosticContext {
+            txt: "a = ;\nb = 1 +;",
+            tree: &tree,
+            event: None,
+            objects: &ObjectIndex::default(),
+            gm_manual: &gm_doc::Program::default(),
+            symbols: &SymbolIndex::default(),
+            assets: &BTreeSet::new(),
+        });
+
+        assert_eq!(output.len(), 2);
+        assert!(output[0].range.start.line < output[1].range.start.line);
+        assert_eq!(output[0].source.as_deref(), Some("gm-code"));
+        assert_eq!(
+            output[0].code,
+            Some(NumberOrString::String("syntax".to_string()))
+        );
+    }
+}
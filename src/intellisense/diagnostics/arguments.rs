@@ -0,0 +1,372 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use super::{DiagnosticContext, Rule};
+use crate::gml::*;
+use crate::intellisense::utils::gm_type_union;
+
+/// Checks calls to builtin functions against what the manual says they take: how many
+/// arguments, and roughly what type each one is.
+pub struct BuiltinArguments;
+
+impl Rule for BuiltinArguments {
+    fn name(&self) -> &'static str {
+        "builtin-arguments"
+    }
+
+    fn check(&self, context: &DiagnosticContext<'_>, output: &mut Vec<Diagnostic>) {
+        for call in context.builtin_calls() {
+            let func = call.func;
+            check_count(context, call.callee, call.args, func, output);
+
+            for (arg, param) in call.args.iter().zip(&func.parameters) {
+                let Some(actual) = ArgType::of(context, arg) else {
+                    continue;
+                };
+                if accepts(&param.gm_type, actual) {
+                    continue;
+                }
+
+                output.push(context.diagnostic(
+                    arg.span,
+                    DiagnosticSeverity::WARNING,
+                    format!(
+                        "`{}` expects `{}` for `{}`, but this is {}",
+                        func.name,
+                        param.gm_type,
+                        param.name,
+                        actual.description()
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn check_count(
+    context: &DiagnosticContext<'_>,
+    callee: &Expr,
+    args: &[Expr],
+    func: &gm_doc::Function,
+    output: &mut Vec<Diagnostic>,
+) {
+    let params = &func.parameters;
+    let required = params.iter().filter(|v| v.optional == false).count();
+    let variadic = params.iter().any(|v| v.name.contains("..."));
+
+    if args.len() < required {
+        let at_least = if required == params.len() {
+            ""
+        } else {
+            "at least "
+        };
+        output.push(context.diagnostic(
+            callee.span,
+            DiagnosticSeverity::ERROR,
+            format!(
+                "`{}` expects {}{}, but got {}",
+                func.name,
+                at_least,
+                plural(required, "argument"),
+                args.len()
+            ),
+        ));
+    } else if args.len() > params.len() && variadic == false {
+        let extra = Span::new(args[params.len()].span.start, args[args.len() - 1].span.end);
+        let at_most = if required == params.len() {
+            ""
+        } else {
+            "at most "
+        };
+        output.push(context.diagnostic(
+            extra,
+            DiagnosticSeverity::ERROR,
+            format!(
+                "`{}` expects {}{}, but got {}",
+                func.name,
+                at_most,
+                plural(params.len(), "argument"),
+                args.len()
+            ),
+        ));
+    }
+}
+
+fn plural(count: usize, word: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, word)
+    } else {
+        format!("{} {}s", count, word)
+    }
+}
+
+/// The type of an argument, for the arguments whose type is obvious from the source alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArgType<'a> {
+    String,
+    Real,
+    Array,
+    Struct,
+    /// The name of an object.
+    Object,
+    /// A builtin constant, with its class, like `Color` for `c_red`.
+    Constant(&'a str),
+}
+
+impl<'a> ArgType<'a> {
+    fn of(context: &DiagnosticContext<'a>, expr: &Expr) -> Option<Self> {
+        match &expr.kind {
+            ExprKind::String(_) | ExprKind::TemplateString(_) => Some(Self::String),
+            ExprKind::Number(_) => Some(Self::Real),
+            ExprKind::Array(_) => Some(Self::Array),
+            ExprKind::Struct(_) => Some(Self::Struct),
+            ExprKind::Grouping(inner) => Self::of(context, inner),
+            ExprKind::Unary {
+                op: UnaryOp::Negate | UnaryOp::Plus,
+                expr,
+            } => Self::of(context, expr).filter(|v| *v == Self::Real),
+            ExprKind::Ident(ident) => {
+                if context.tree.local(&ident.name, ident.span.start).is_some() {
+                    return None;
+                }
+                if context.objects.get(&ident.name).is_some() {
+                    return Some(Self::Object);
+                }
+
+                let constant = context.gm_manual.constants.get(&ident.name)?;
+                constant.class.as_deref().map(Self::Constant)
+            }
+            _ => None,
+        }
+    }
+
+    fn description(self) -> String {
+        match self {
+            Self::String => "a string".to_string(),
+            Self::Real => "a number".to_string(),
+            Self::Array => "an array".to_string(),
+            Self::Struct => "a struct".to_string(),
+            Self::Object => "an object".to_string(),
+            Self::Constant(class) => format!("a `{}` constant", class),
+        }
+    }
+}
+
+/// Whether an argument could be what a parameter wants. Anything we don't understand is given
+/// the benefit of the doubt, since we only want to point out the obvious mistakes.
+fn accepts(gm_type: &str, actual: ArgType) -> bool {
+    gm_type_union(gm_type).into_iter().any(|expected| {
+        let numeric = matches!(expected, "Real" | "Bool")
+            || expected.starts_with("Id.")
+            || expected.starts_with("Asset.")
+            || expected.starts_with("Constant.");
+        let known = numeric || matches!(expected, "String" | "Array" | "Struct");
+        if known == false {
+            return true;
+        }
+
+        match actual {
+            ArgType::String => expected == "String",
+            ArgType::Array => expected == "Array",
+            ArgType::Struct => expected == "Struct",
+            // handles are numbers under the hood, and people do pass `-1` and `noone`
+            ArgType::Real => numeric,
+            // objects can be used in place of their instances
+            ArgType::Object => matches!(
+                expected,
+                "Asset.GMObject" | "Id.Instance" | "Real" | "Asset" | "Asset.GMAsset"
+            ),
+            // constants are numbers too, but one from the wrong class is a mistake
+            ArgType::Constant(class) => match expected.strip_prefix("Constant.") {
+                Some(expected) => expected == class,
+                None => numeric && expected.starts_with("Asset.") == false,
+            },
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{check, manual};
+    use super::*;
+
+    #[test]
+    fn builtin_arguments() {
+        let gm_manual = manual(&[
+            (
+                "draw_sprite",
+                &[
+                    ("sprite", "Asset.GMSprite", false),
+                    ("subimg", "Real", false),
+                    ("x", "Real", false),
+                    ("y", "Real", false),
+                ],
+            ),
+            (
+                "string_pos",
+                &[("substr", "String", false), ("str", "String", false)],
+            ),
+            (
+                "instance_create_depth",
+                &[
+                    ("x", "Real", false),
+                    ("y", "Real", false),
+                    ("depth", "Real", false),
+                    ("obj", "Asset.GMObject", false),
+                    ("var_struct", "Struct", true),
+                ],
+            ),
+            (
+                "array_push",
+                &[("array", "Array<Any>", false), ("...", "Any", true)],
+            ),
+        ]);
+        let messages = |txt: &str| -> Vec<String> {
+            check(BuiltinArguments, txt, &gm_manual)
+                .into_iter()
+                .map(|v| v.message)
+                .collect()
+        };
+
+        assert!(messages("draw_sprite(spr_player, 0, x, -y);").is_empty());
+        assert!(messages("instance_create_depth(0, 0, 0, obj_player, { hp: 1 });").is_empty());
+        assert!(messages("array_push([], 1, 2, 3, \"four\");").is_empty());
+
+        assert_eq!(
+            messages("draw_sprite(spr_player, 0);"),
+            vec!["`draw_sprite` expects 4 arguments, but got 2"]
+        );
+        assert_eq!(
+            messages("instance_create_depth(0, 0, 0, obj_player, {}, 1);"),
+            vec!["`instance_create_depth` expects at most 5 arguments, but got 6"]
+        );
+        assert_eq!(
+            messages("string_pos(1, \"hello\");"),
+            vec!["`string_pos` expects `String` for `substr`, but this is a number"]
+        );
+        assert_eq!(
+            messages("draw_sprite(obj_player, \"0\", 0, 0);"),
+            vec![
+                "`draw_sprite` expects `Asset.GMSprite` for `sprite`, but this is an object",
+                "`draw_sprite` expects `Real` for `subimg`, but this is a string",
+            ]
+        );
+        assert!(messages("var obj_player = 1; draw_sprite(obj_player, 0, 0, 0);").is_empty());
+    }
+
+    #[test]
+    fn manual_type_unions() {
+        // these are the types as docs.json gives them
+        let gm_manual = manual(&[
+            (
+                "place_meeting",
+                &[
+                    ("x", "Real", false),
+                    ("y", "Real", false),
+                    (
+                        "obj",
+                        "Id.TileMapElement,Asset.GMObject,Id.Instance,Constant.All,Array",
+                        false,
+                    ),
+                ],
+            ),
+            (
+                "instance_exists",
+                &[("obj", "Id.Instance,Asset.GMObject,Undefined", false)],
+            ),
+            (
+                "instance_destroy",
+                &[("id", "Id.Instance,Asset.GMObject", true)],
+            ),
+            (
+                "collision_point",
+                &[
+                    ("x", "Real", false),
+                    ("y", "Real", false),
+                    (
+                        "obj",
+                        "Id.TileMapElement,Asset.GMObject,Id.Instance,Constant.All,Array",
+                        false,
+                    ),
+                    ("prec", "Bool", false),
+                    ("notme", "Bool", false),
+                ],
+            ),
+            ("draw_sprite_ext", &[("sprite", "Asset.GMSprite", false)]),
+            ("array_length", &[("variable", "Array[Any]", false)]),
+            (
+                "ds_list_add",
+                &[("id", "Id.DsList", false), ("value", "[Any]", false)],
+            ),
+        ]);
+        let messages = |txt: &str| -> Vec<String> {
+            check(BuiltinArguments, txt, &gm_manual)
+                .into_iter()
+                .map(|v| v.message)
+                .collect()
+        };
+
+        assert!(messages("place_meeting(x, y, obj_player);").is_empty());
+        assert!(messages("instance_exists(obj_player);").is_empty());
+        assert!(messages("instance_destroy(obj_player);").is_empty());
+        assert!(messages("collision_point(x, y, [obj_player], false, true);").is_empty());
+        assert!(messages("array_length([1, 2]);").is_empty());
+        assert!(messages("ds_list_add(list, \"a\");").is_empty());
+
+        assert_eq!(
+            messages("place_meeting(x, y, \"obj_player\");"),
+            vec!["`place_meeting` expects `Id.TileMapElement,Asset.GMObject,Id.Instance,Constant.All,Array` for `obj`, but this is a string"]
+        );
+        assert_eq!(
+            messages("draw_sprite_ext(obj_player);"),
+            vec!["`draw_sprite_ext` expects `Asset.GMSprite` for `sprite`, but this is an object"]
+        );
+        assert_eq!(
+            messages("array_length(\"abc\");"),
+            vec!["`array_length` expects `Array[Any]` for `variable`, but this is a string"]
+        );
+    }
+
+    #[test]
+    fn constant_classes() {
+        let mut gm_manual = manual(&[
+            ("draw_set_colour", &[("col", "Constant.Color", false)]),
+            ("draw_set_halign", &[("halign", "Constant.HAlign", false)]),
+            ("draw_set_alpha", &[("alpha", "Real", false)]),
+            (
+                "keyboard_check",
+                &[("key", "Constant.VirtualKey,Real", false)],
+            ),
+        ]);
+        for (name, class) in [
+            ("c_red", "Color"),
+            ("fa_left", "HAlign"),
+            ("vk_left", "VirtualKey"),
+        ] {
+            gm_manual.constants.insert(
+                name.to_string(),
+                gm_doc::Constant {
+                    name: name.to_string(),
+                    description: String::new(),
+                    returns: String::new(),
+                    class: Some(class.to_string()),
+                    deprecated: false,
+                    link: None,
+                },
+            );
+        }
+        let messages = |txt: &str| -> Vec<String> {
+            check(BuiltinArguments, txt, &gm_manual)
+                .into_iter()
+                .map(|v| v.message)
+                .collect()
+        };
+
+        assert!(messages("draw_set_colour(c_red); draw_set_halign(fa_left);").is_empty());
+        assert!(messages("draw_set_colour($ff0000); draw_set_alpha(c_red);").is_empty());
+        assert!(messages("keyboard_check(vk_left); keyboard_check(37);").is_empty());
+        assert_eq!(
+            messages("draw_set_halign(c_red);"),
+            vec!["`draw_set_halign` expects `Constant.HAlign` for `halign`, but this is a `Color` constant"]
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag};
+
+use super::{DiagnosticContext, Rule};
+
+/// Warns about calls to builtins which the manual says are deprecated.
+pub struct Deprecated;
+
+impl Rule for Deprecated {
+    fn name(&self) -> &'static str {
+        "deprecated"
+    }
+
+    fn check(&self, context: &DiagnosticContext<'_>, output: &mut Vec<Diagnostic>) {
+        for call in context.builtin_calls() {
+            if call.func.deprecated == false {
+                continue;
+            }
+
+            output.push(Diagnostic {
+                tags: Some(vec![DiagnosticTag::DEPRECATED]),
+                ..context.diagnostic(
+                    call.callee.span,
+                    DiagnosticSeverity::WARNING,
+                    format!("`{}` is deprecated", call.func.name),
+                )
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{check, manual};
+    use super::*;
+
+    #[test]
+    fn deprecated() {
+        let mut gm_manual = manual(&[("array_height_2d", &[("variable", "Array", false)])]);
+        let output = check(Deprecated, "var a = array_height_2d([]);", &gm_manual);
+        assert!(output.is_empty());
+
+        gm_manual
+            .functions
+            .get_mut("array_height_2d")
+            .unwrap()
+            .deprecated = true;
+        let output = check(Deprecated, "var a = array_height_2d([]);", &gm_manual);
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].message, "`array_height_2d` is deprecated");
+        assert_eq!(output[0].tags, Some(vec![DiagnosticTag::DEPRECATED]));
+        assert_eq!(output[0].range.start.character, 8);
+    }
+}
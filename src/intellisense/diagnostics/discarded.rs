@@ -0,0 +1,111 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity, TextEdit};
+
+use super::{DiagnosticContext, QuickFix, Rule};
+use crate::gml::*;
+
+/// Warns about calls to pure builtins, like `string_upper(name);`, whose result is thrown away.
+/// The author nearly always meant to assign it back, so we offer to do that.
+pub struct DiscardedResult;
+
+impl Rule for DiscardedResult {
+    fn name(&self) -> &'static str {
+        "discarded-result"
+    }
+
+    fn check(&self, context: &DiagnosticContext<'_>, output: &mut Vec<Diagnostic>) {
+        let mut finder = DiscardedFinder {
+            context,
+            found: vec![],
+        };
+        for stmt in &context.tree.stmts {
+            finder.visit_stmt(stmt);
+        }
+
+        for (call, args, func) in finder.found {
+            let mut diagnostic = context.diagnostic(
+                call.span,
+                DiagnosticSeverity::WARNING,
+                format!(
+                    "`{}` doesn't change its arguments, so its result is thrown away",
+                    func.name
+                ),
+            );
+
+            // assigning back only makes sense if the first argument is somewhere to assign to
+            let first = args
+                .first()
+                .filter(|v| {
+                    matches!(
+                        v.kind,
+                        ExprKind::Ident(_) | ExprKind::Field { .. } | ExprKind::Index { .. }
+                    )
+                })
+                .map(|v| &context.txt[v.span.start..v.span.end]);
+            if let Some(first) = first {
+                let start = diagnostic.range.start;
+                let fix = QuickFix {
+                    title: format!("Assign the result to `{}`", first),
+                    edits: vec![TextEdit {
+                        range: lsp_types::Range { start, end: start },
+                        new_text: format!("{} = ", first),
+                    }],
+                };
+                diagnostic.data = serde_json::to_value(fix).ok();
+            }
+
+            output.push(diagnostic);
+        }
+    }
+}
+
+struct DiscardedFinder<'a, 'b> {
+    context: &'b DiagnosticContext<'a>,
+    found: Vec<(&'a Expr, &'a [Expr], &'a gm_doc::Function)>,
+}
+
+impl<'a> Visitor<'a> for DiscardedFinder<'a, '_> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        if let StmtKind::Expr(expr) = &stmt.kind {
+            if let ExprKind::Call { callee, args, .. } = &expr.kind {
+                if let ExprKind::Ident(ident) = &callee.kind {
+                    let func = self.context.gm_manual.functions.get(&ident.name);
+                    let local = self.context.tree.local(&ident.name, ident.span.start);
+                    if let Some(func) = func.filter(|v| v.pure && local.is_none()) {
+                        self.found.push((expr, args, func));
+                    }
+                }
+            }
+        }
+
+        walk_stmt(self, stmt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{check, manual};
+    use super::*;
+
+    #[test]
+    fn discarded_result() {
+        let mut gm_manual = manual(&[
+            ("string_upper", &[("str", "String", false)]),
+            ("show_debug_message", &[("str", "Any", false)]),
+        ]);
+        gm_manual.functions.get_mut("string_upper").unwrap().pure = true;
+
+        let txt = "name = string_upper(name);\nshow_debug_message(name);\nstring_upper(name);";
+        let output = check(DiscardedResult, txt, &gm_manual);
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].range.start.line, 2);
+
+        let fix: QuickFix = serde_json::from_value(output[0].data.clone().unwrap()).unwrap();
+        assert_eq!(fix.title, "Assign the result to `name`");
+        assert_eq!(fix.edits[0].new_text, "name = ");
+        assert_eq!(fix.edits[0].range.start, output[0].range.start);
+
+        let output = check(DiscardedResult, "string_upper(\"a\");", &gm_manual);
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].data, None);
+    }
+}
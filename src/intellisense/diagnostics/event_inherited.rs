@@ -0,0 +1,111 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use super::{DiagnosticContext, Rule};
+use crate::gml::*;
+
+/// Warns about `event_inherited()` in an event which no ancestor of the object has.
+pub struct EventInherited;
+
+impl Rule for EventInherited {
+    fn name(&self) -> &'static str {
+        "event-inherited"
+    }
+
+    fn check(&self, context: &DiagnosticContext<'_>, output: &mut Vec<Diagnostic>) {
+        let Some((object, event)) = context.event else {
+            return;
+        };
+        if context
+            .objects
+            .inherited_event(&object.name, event)
+            .is_some()
+        {
+            return;
+        }
+
+        let mut finder = CallFinder {
+            name: "event_inherited",
+            found: vec![],
+        };
+        for stmt in &context.tree.stmts {
+            finder.visit_stmt(stmt);
+        }
+
+        for span in finder.found {
+            output.push(context.diagnostic(
+                span,
+                DiagnosticSeverity::WARNING,
+                format!(
+                    "`event_inherited()` does nothing: no ancestor of `{}` has this event",
+                    object.name
+                ),
+            ));
+        }
+    }
+}
+
+/// Finds the callee of every call to a function.
+struct CallFinder<'a> {
+    name: &'a str,
+    found: Vec<Span>,
+}
+
+impl<'a> Visitor<'a> for CallFinder<'_> {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if let ExprKind::Call { callee, .. } = &expr.kind {
+            if matches!(&callee.kind, ExprKind::Ident(v) if v.name == self.name) {
+                self.found.push(callee.span);
+            }
+        }
+
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use yy_typings::EventType;
+
+    use super::super::tests::run_one;
+    use super::*;
+    use crate::services::{EventInfo, ObjectIndex, ObjectInfo, SymbolIndex};
+
+    #[test]
+    fn event_inherited() {
+        let mut objects = ObjectIndex::default();
+        let mut parent = ObjectInfo::new("obj_parent".to_string(), []);
+        parent.events = vec![EventInfo {
+            event_type: EventType::Create,
+            path: "Create_0.gml".into(),
+            collision_object: None,
+        }];
+        let mut child = ObjectInfo::new("obj_child".to_string(), []);
+        child.parent = Some("obj_parent".to_string());
+        objects.insert(parent);
+        objects.insert(child.clone());
+
+        let txt = "if (true) {\n    event_inherited();\n}";
+        let tree = SyntaxTree::parse(txt);
+        let check = |event| {
+            let context = DiagnosticContext {
+                txt,
+                tree: &tree,
+                event: Some((&child, event)),
+                objects: &objects,
+                gm_manual: &gm_doc::Program::default(),
+                symbols: &SymbolIndex::default(),
+                assets: &BTreeSet::new(),
+            };
+            run_one(EventInherited, &context)
+        };
+
+        assert!(check(EventType::Create).is_empty());
+
+        let output = check(EventType::Destroy);
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].range.start.line, 1);
+        assert_eq!(output[0].range.start.character, 4);
+    }
+}
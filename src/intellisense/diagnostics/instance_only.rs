@@ -0,0 +1,150 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use super::{DiagnosticContext, Rule};
+use crate::gml::*;
+
+/// Warns about builtin instance variables, like `x` or `object_index`, in the top level code of a
+/// script. That runs when the game starts, before there are any instances for it to run in.
+///
+/// The same goes for free functions, declared at the top level of a script, which have no
+/// instance of their own. Constructors and methods do, so they're left alone, and so is the body
+/// of a `with`.
+pub struct InstanceOnly;
+
+impl Rule for InstanceOnly {
+    fn name(&self) -> &'static str {
+        "instance-only"
+    }
+
+    fn check(&self, context: &DiagnosticContext<'_>, output: &mut Vec<Diagnostic>) {
+        // every gml document which isn't an event is a script
+        if context.event.is_some() {
+            return;
+        }
+
+        let mut finder = InstanceVariableFinder {
+            context,
+            function: None,
+            found: vec![],
+        };
+        for stmt in &context.tree.stmts {
+            match &stmt.kind {
+                StmtKind::Function(func) if func.constructor == false => {
+                    finder.function = func.name.as_ref().map(|v| v.name.as_str());
+                    walk_function(&mut finder, func);
+                    finder.function = None;
+                }
+                _ => finder.visit_stmt(stmt),
+            }
+        }
+
+        for (ident, function) in finder.found {
+            let message = match function {
+                Some(function) => format!(
+                    "`{}` belongs to an instance, but `{}` is a free function with no instance of its own",
+                    ident.name, function
+                ),
+                None => format!(
+                    "`{}` belongs to an instance, but a script's top level code doesn't run in one",
+                    ident.name
+                ),
+            };
+            output.push(context.diagnostic(ident.span, DiagnosticSeverity::WARNING, message));
+        }
+    }
+}
+
+struct InstanceVariableFinder<'a, 'b> {
+    context: &'b DiagnosticContext<'a>,
+    /// The free function we're in, if any.
+    function: Option<&'a str>,
+    found: Vec<(&'a Ident, Option<&'a str>)>,
+}
+
+impl<'a> Visitor<'a> for InstanceVariableFinder<'a, '_> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match &stmt.kind {
+            StmtKind::With { target, .. } => self.visit_expr(target),
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match &expr.kind {
+            ExprKind::Ident(ident) => {
+                let variable = self.context.gm_manual.variables.get(&ident.name);
+                let local = self.context.tree.local(&ident.name, ident.span.start);
+                if variable.is_some_and(|v| v.instance) && local.is_none() {
+                    self.found.push((ident, self.function));
+                }
+            }
+            // calling a variable isn't what we're looking for
+            ExprKind::Call { callee, args, .. } => {
+                if matches!(callee.kind, ExprKind::Ident(_)) == false {
+                    self.visit_expr(callee);
+                }
+                for arg in args {
+                    self.visit_expr(arg);
+                }
+            }
+            _ => walk_expr(self, expr),
+        }
+    }
+
+    // constructors, methods and functions nested in other functions all have an instance
+    fn visit_function(&mut self, _: &'a FunctionDecl) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use yy_typings::EventType;
+
+    use super::super::tests::{check, manual_variables, run_one};
+    use super::*;
+    use crate::services::{ObjectIndex, ObjectInfo, SymbolIndex};
+
+    #[test]
+    fn instance_only() {
+        let gm_manual = manual_variables(&[("x", true, true), ("fps", false, false)]);
+        let messages = |txt: &str| -> Vec<String> {
+            check(InstanceOnly, txt, &gm_manual)
+                .into_iter()
+                .map(|v| v.message)
+                .collect()
+        };
+
+        assert_eq!(
+            messages("x = 1;"),
+            vec!["`x` belongs to an instance, but a script's top level code doesn't run in one"]
+        );
+        assert!(messages("var a = fps;").is_empty());
+        assert_eq!(
+            messages("function f() { return x; }"),
+            vec!["`x` belongs to an instance, but `f` is a free function with no instance of its own"]
+        );
+        assert!(messages("function Point() constructor { x = 1; }").is_empty());
+        assert!(messages("function f() { return function() { return x; }; }").is_empty());
+        assert!(messages("function f() { with (obj_player) { x = 1; } }").is_empty());
+        assert!(messages("m = function() { return x; };").is_empty());
+        assert!(messages("with (obj_player) { x = 1; }").is_empty());
+        assert!(messages("var x = 1; show_debug_message(x);").is_empty());
+
+        // events always run in an instance
+        let txt = "x = 1;";
+        let tree = SyntaxTree::parse(txt);
+        let objects = ObjectIndex::default();
+        let object = ObjectInfo::new("obj_player".to_string(), []);
+        let context = DiagnosticContext {
+            txt,
+            tree: &tree,
+            event: Some((&object, EventType::Create)),
+            objects: &objects,
+            gm_manual: &gm_manual,
+            symbols: &SymbolIndex::default(),
+            assets: &BTreeSet::new(),
+        };
+        assert!(run_one(InstanceOnly, &context).is_empty());
+    }
+}
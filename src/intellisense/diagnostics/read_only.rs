@@ -0,0 +1,130 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use super::{DiagnosticContext, Rule};
+use crate::gml::*;
+
+/// Errors on assignments to builtin variables which can only be read, like `object_index`.
+pub struct ReadOnly;
+
+impl Rule for ReadOnly {
+    fn name(&self) -> &'static str {
+        "read-only"
+    }
+
+    fn check(&self, context: &DiagnosticContext<'_>, output: &mut Vec<Diagnostic>) {
+        let mut finder = WriteFinder {
+            context,
+            found: vec![],
+        };
+        for stmt in &context.tree.stmts {
+            finder.visit_stmt(stmt);
+        }
+
+        for (span, variable) in finder.found {
+            output.push(context.diagnostic(
+                span,
+                DiagnosticSeverity::ERROR,
+                format!("`{}` is read-only", variable.name),
+            ));
+        }
+    }
+}
+
+/// Finds every write to a read-only builtin.
+struct WriteFinder<'a, 'b> {
+    context: &'b DiagnosticContext<'a>,
+    found: Vec<(Span, &'a gm_doc::Field)>,
+}
+
+impl<'a> WriteFinder<'a, '_> {
+    fn written(&mut self, target: &'a Expr) {
+        let ident = match &target.kind {
+            ExprKind::Ident(ident) => {
+                if self
+                    .context
+                    .tree
+                    .local(&ident.name, ident.span.start)
+                    .is_some()
+                {
+                    return;
+                }
+                ident
+            }
+            // `other.object_index` is no more writable than `object_index`
+            ExprKind::Field { field, .. } => field,
+            ExprKind::Index { expr, .. } | ExprKind::Grouping(expr) => {
+                return self.written(expr);
+            }
+            _ => return,
+        };
+
+        let variable = self.context.gm_manual.variables.get(&ident.name);
+        if let Some(variable) = variable.filter(|v| v.set == false) {
+            if matches!(target.kind, ExprKind::Field { .. }) && variable.instance == false {
+                return;
+            }
+            self.found.push((ident.span, variable));
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for WriteFinder<'a, '_> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        if let StmtKind::Assign { target, .. } = &stmt.kind {
+            self.written(target);
+        }
+
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match &expr.kind {
+            ExprKind::Postfix { expr: target, .. }
+            | ExprKind::Unary {
+                op: UnaryOp::PreIncrement | UnaryOp::PreDecrement,
+                expr: target,
+            } => self.written(target),
+            _ => {}
+        }
+
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{check, manual_variables};
+    use super::*;
+
+    #[test]
+    fn read_only() {
+        let gm_manual = manual_variables(&[
+            ("object_index", false, true),
+            ("x", true, true),
+            ("fps", false, false),
+        ]);
+        let messages = |txt: &str| -> Vec<String> {
+            check(ReadOnly, txt, &gm_manual)
+                .into_iter()
+                .map(|v| v.message)
+                .collect()
+        };
+
+        assert!(messages("x = 1; x += 2; var a = object_index + fps;").is_empty());
+        assert!(messages("var fps = 1; fps = 2;").is_empty());
+        assert!(messages("if (true) { var fps = 1; } fps = 2;").is_empty());
+        assert_eq!(
+            messages("fps = 1; var fps = 2;"),
+            vec!["`fps` is read-only"]
+        );
+        assert_eq!(
+            messages("object_index = 1;"),
+            vec!["`object_index` is read-only"]
+        );
+        assert_eq!(messages("fps++;"), vec!["`fps` is read-only"]);
+        assert_eq!(
+            messages("other.object_index = 1; other.fps = 2;"),
+            vec!["`object_index` is read-only"]
+        );
+    }
+}
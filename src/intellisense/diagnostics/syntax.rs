@@ -0,0 +1,22 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use super::{DiagnosticContext, Rule};
+
+/// Reports whatever the parser couldn't make sense of.
+pub struct SyntaxErrors;
+
+impl Rule for SyntaxErrors {
+    fn name(&self) -> &'static str {
+        "syntax"
+    }
+
+    fn check(&self, context: &DiagnosticContext<'_>, output: &mut Vec<Diagnostic>) {
+        for error in &context.tree.errors {
+            output.push(context.diagnostic(
+                error.span,
+                DiagnosticSeverity::ERROR,
+                error.message.clone(),
+            ));
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeSet;
+
+use lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use super::{DiagnosticContext, Rule};
+use crate::gml::*;
+
+/// Names which are always defined, but which the manual doesn't list as variables.
+const KEYWORDS: [&str; 10] = [
+    "self",
+    "other",
+    "global",
+    "all",
+    "noone",
+    "true",
+    "false",
+    "undefined",
+    "argument",
+    "argument_count",
+];
+
+/// Warns about identifiers which don't resolve to anything: not a builtin, an asset, something
+/// declared in the project, a local, or a variable which some instance has.
+///
+/// Names which are only made at runtime, with `variable_instance_set` and friends, can be
+/// allowed by name, or by prefix with a trailing `*`.
+pub struct UndefinedIdentifiers {
+    pub allowed: Vec<String>,
+}
+
+impl UndefinedIdentifiers {
+    fn is_allowed(&self, name: &str) -> bool {
+        self.allowed.iter().any(|v| match v.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => v == name,
+        })
+    }
+}
+
+impl Rule for UndefinedIdentifiers {
+    fn name(&self) -> &'static str {
+        "undefined"
+    }
+
+    fn check(&self, context: &DiagnosticContext<'_>, output: &mut Vec<Diagnostic>) {
+        let mut declared = DeclaredNames::default();
+        let mut used = UsedNames::default();
+        for stmt in &context.tree.stmts {
+            declared.visit_stmt(stmt);
+            used.visit_stmt(stmt);
+        }
+
+        for ident in used.found {
+            let name = ident.name.as_str();
+            let defined = KEYWORDS.contains(&name)
+                || is_numbered_argument(name)
+                || declared.names.contains(name)
+                || context.gm_manual.functions.contains_key(name)
+                || context.gm_manual.variables.contains_key(name)
+                || context.gm_manual.constants.contains_key(name)
+                || context.assets.contains(name)
+                || context.symbols.get(name).is_empty() == false
+                || context.objects.any_declares(name)
+                || context.tree.local(name, ident.span.start).is_some()
+                || self.is_allowed(name);
+
+            if defined == false {
+                output.push(context.diagnostic(
+                    ident.span,
+                    DiagnosticSeverity::WARNING,
+                    format!("`{}` isn't declared anywhere in the project", name),
+                ));
+            }
+        }
+    }
+}
+
+/// `argument0` through `argument15`.
+fn is_numbered_argument(name: &str) -> bool {
+    name.strip_prefix("argument")
+        .and_then(|v| v.parse::<u8>().ok())
+        .is_some_and(|v| v < 16)
+}
+
+/// Everything this document declares. Any assignment to a bare name declares an instance
+/// variable, and a `var` can be seen from the rest of its function even outside of its block, so
+/// we're generous here rather than risk a false positive.
+#[derive(Default)]
+struct DeclaredNames<'a> {
+    names: BTreeSet<&'a str>,
+}
+
+impl<'a> Visitor<'a> for DeclaredNames<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match &stmt.kind {
+            StmtKind::Assign {
+                target:
+                    Expr {
+                        kind: ExprKind::Ident(ident),
+                        ..
+                    },
+                ..
+            } => {
+                self.names.insert(&ident.name);
+            }
+            StmtKind::Var(decls) | StmtKind::Static(decls) => {
+                self.names
+                    .extend(decls.iter().map(|v| v.name.name.as_str()));
+            }
+            StmtKind::GlobalVar(idents) => {
+                self.names.extend(idents.iter().map(|v| v.name.as_str()));
+            }
+            _ => {}
+        }
+
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_function(&mut self, func: &'a FunctionDecl) {
+        if let Some(name) = &func.name {
+            self.names.insert(&name.name);
+        }
+
+        walk_function(self, func);
+    }
+}
+
+/// Every identifier which is read or written as a variable, or called.
+#[derive(Default)]
+struct UsedNames<'a> {
+    found: Vec<&'a Ident>,
+}
+
+impl<'a> Visitor<'a> for UsedNames<'a> {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if let ExprKind::Ident(ident) = &expr.kind {
+            self.found.push(ident);
+        }
+
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{manual, run_one};
+    use super::*;
+    use crate::services::{ObjectIndex, ObjectInfo, SymbolIndex};
+
+    #[test]
+    fn undefined() {
+        let gm_manual = manual(&[("instance_destroy", &[])]);
+        let mut objects = ObjectIndex::default();
+        objects.insert(ObjectInfo::new(
+            "obj_player".to_string(),
+            [("hp".to_string(), "10".to_string())],
+        ));
+        let assets: BTreeSet<String> = ["spr_player".to_string()].into();
+        let symbols = SymbolIndex::default();
+
+        let messages = |txt: &str| -> Vec<String> {
+            let tree = SyntaxTree::parse(txt);
+            let context = DiagnosticContext {
+                txt,
+                tree: &tree,
+                event: None,
+                objects: &objects,
+                gm_manual: &gm_manual,
+                symbols: &symbols,
+                assets: &assets,
+            };
+            let rule = UndefinedIdentifiers {
+                allowed: vec!["dyn_*".to_string(), "magic".to_string()],
+            };
+            run_one(rule, &context)
+                .into_iter()
+                .map(|v| v.message)
+                .collect()
+        };
+
+        assert_eq!(
+            messages("instnace_destroy();"),
+            vec!["`instnace_destroy` isn't declared anywhere in the project"]
+        );
+        assert!(messages("instance_destroy(); var s = spr_player; hp -= 1;").is_empty());
+        assert!(messages("speed = 2; function f(a) { return a + speed + argument0; }").is_empty());
+        assert!(
+            messages("if (true) { var a = 1; } else { var a = 2; } f(a); function f() {}")
+                .is_empty()
+        );
+        assert!(messages("var b = dyn_health + magic; var c = self;").is_empty());
+        assert_eq!(messages("var b = magics;").len(), 1);
+    }
+}
@@ -0,0 +1,124 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag};
+
+use super::{DiagnosticContext, Rule};
+use crate::gml::*;
+
+/// Warns about code after a `return`, `exit`, `break`, `continue` or `throw` in the same block.
+pub struct Unreachable;
+
+impl Rule for Unreachable {
+    fn name(&self) -> &'static str {
+        "unreachable"
+    }
+
+    fn check(&self, context: &DiagnosticContext<'_>, output: &mut Vec<Diagnostic>) {
+        let mut finder = UnreachableFinder { found: vec![] };
+        finder.visit_stmts(&context.tree.stmts);
+
+        for span in finder.found {
+            output.push(Diagnostic {
+                tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                ..context.diagnostic(
+                    span,
+                    DiagnosticSeverity::WARNING,
+                    "Unreachable code".to_string(),
+                )
+            });
+        }
+    }
+}
+
+struct UnreachableFinder {
+    found: Vec<Span>,
+}
+
+impl UnreachableFinder {
+    /// Checks a list of statements which run one after the other.
+    fn visit_stmts(&mut self, stmts: &[Stmt]) {
+        let jump = stmts.iter().position(|v| {
+            matches!(
+                v.kind,
+                StmtKind::Return(_)
+                    | StmtKind::Exit
+                    | StmtKind::Break
+                    | StmtKind::Continue
+                    | StmtKind::Throw(_)
+            )
+        });
+
+        if let Some(jump) = jump {
+            // declarations aren't run, so they aren't unreachable
+            let unreachable: Vec<_> = stmts[jump + 1..]
+                .iter()
+                .filter(|v| {
+                    matches!(
+                        v.kind,
+                        StmtKind::Function(_)
+                            | StmtKind::Enum(_)
+                            | StmtKind::Macro(_)
+                            | StmtKind::Empty
+                    ) == false
+                })
+                .collect();
+
+            if let Some((first, last)) = unreachable.first().zip(unreachable.last()) {
+                self.found.push(Span::new(first.span.start, last.span.end));
+            }
+        }
+
+        for stmt in stmts {
+            self.visit_stmt(stmt);
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for UnreachableFinder {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match &stmt.kind {
+            StmtKind::Switch { value, cases } => {
+                self.visit_expr(value);
+                for case in cases {
+                    self.visit_stmts(&case.body);
+                }
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_block(&mut self, block: &'a Block) {
+        self.visit_stmts(&block.stmts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::check;
+    use super::*;
+
+    #[test]
+    fn unreachable() {
+        let txt = r#"
+function f(a) {
+    if (a) {
+        return 1;
+        a += 1;
+        show_debug_message(a);
+    }
+    switch (a) {
+        case 1: break; a = 2;
+        case 2: return 3;
+    }
+    return 2;
+    function inner() {}
+}
+exit;
+"#;
+        let output = check(Unreachable, txt, &gm_doc::Program::default());
+        let lines: Vec<_> = output
+            .iter()
+            .map(|v| (v.range.start.line, v.range.end.line))
+            .collect();
+        assert_eq!(lines, vec![(4, 5), (8, 8)]);
+        assert_eq!(output[0].tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+    }
+}
@@ -0,0 +1,162 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag};
+
+use super::{DiagnosticContext, Rule};
+use crate::gml::*;
+
+/// Warns about `var`s which are never read, and parameters which are never used.
+///
+/// A `var` can be read from anywhere later in its function, even outside of the block it was
+/// declared in, so this works a function at a time rather than a block at a time.
+pub struct UnusedLocals;
+
+impl Rule for UnusedLocals {
+    fn name(&self) -> &'static str {
+        "unused"
+    }
+
+    fn check(&self, context: &DiagnosticContext<'_>, output: &mut Vec<Diagnostic>) {
+        let mut finder = UnusedFinder {
+            scopes: vec![FunctionScope::default()],
+            unused: vec![],
+        };
+        for stmt in &context.tree.stmts {
+            finder.visit_stmt(stmt);
+        }
+        finder.pop_scope();
+
+        for (ident, kind) in finder.unused {
+            let message = match kind {
+                LocalKind::Param => format!("Parameter `{}` is never used", ident.name),
+                _ => format!("`{}` is declared but never read", ident.name),
+            };
+
+            output.push(Diagnostic {
+                tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                ..context.diagnostic(ident.span, DiagnosticSeverity::WARNING, message)
+            });
+        }
+    }
+}
+
+#[derive(Default)]
+struct FunctionScope<'a> {
+    declared: Vec<(&'a Ident, LocalKind)>,
+    read: Vec<&'a str>,
+}
+
+struct UnusedFinder<'a> {
+    scopes: Vec<FunctionScope<'a>>,
+    unused: Vec<(&'a Ident, LocalKind)>,
+}
+
+impl<'a> UnusedFinder<'a> {
+    fn scope(&mut self) -> &mut FunctionScope<'a> {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+
+        // `argument[0]` and friends use the parameters without naming them
+        let by_position = scope.read.iter().any(|v| {
+            v.strip_prefix("argument")
+                .is_some_and(|v| v == "_count" || v.is_empty() || v.parse::<u8>().is_ok())
+        });
+
+        for (ident, kind) in scope.declared {
+            if kind == LocalKind::Param && by_position {
+                continue;
+            }
+            if scope.read.contains(&ident.name.as_str()) == false {
+                self.unused.push((ident, kind));
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for UnusedFinder<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match &stmt.kind {
+            StmtKind::Var(decls) => {
+                for decl in decls {
+                    self.scope().declared.push((&decl.name, LocalKind::Var));
+                }
+                walk_stmt(self, stmt);
+            }
+            // a plain assignment writes to a local without reading it
+            StmtKind::Assign {
+                target:
+                    Expr {
+                        kind: ExprKind::Ident(_),
+                        ..
+                    },
+                op: AssignOp::Assign,
+                value,
+            } => self.visit_expr(value),
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match &expr.kind {
+            ExprKind::Ident(ident) => self.scope().read.push(&ident.name),
+            // the shorthand `{ a }` reads `a`
+            ExprKind::Struct(fields) => {
+                for field in fields.iter().filter(|v| v.value.is_none()) {
+                    self.scope().read.push(&field.name.name);
+                }
+            }
+            _ => {}
+        }
+
+        walk_expr(self, expr);
+    }
+
+    fn visit_function(&mut self, func: &'a FunctionDecl) {
+        let mut scope = FunctionScope::default();
+        scope
+            .declared
+            .extend(func.params.iter().map(|v| (&v.name, LocalKind::Param)));
+        self.scopes.push(scope);
+
+        walk_function(self, func);
+        self.pop_scope();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::check;
+    use super::*;
+
+    #[test]
+    fn unused() {
+        let messages = |txt: &str| -> Vec<String> {
+            check(UnusedLocals, txt, &gm_doc::Program::default())
+                .into_iter()
+                .map(|v| v.message)
+                .collect()
+        };
+
+        assert!(messages("var a = 1; show_debug_message(a);").is_empty());
+        assert!(messages("if (c) { var a = 1; } else { var a = 2; } f(a);").is_empty());
+        assert!(messages("var a = 1; var s = { a }; f(s);").is_empty());
+        assert!(messages("var a = []; a[0] = 1;").is_empty());
+        assert!(messages("function f(a) { return argument[0]; }").is_empty());
+
+        assert_eq!(
+            messages("var a = 1; a = 2;"),
+            vec!["`a` is declared but never read"]
+        );
+        assert_eq!(
+            messages("var a = 1; function f(b) { return a; }"),
+            vec![
+                "`a` is declared but never read",
+                "Parameter `b` is never used"
+            ]
+        );
+
+        let output = check(UnusedLocals, "var a;", &gm_doc::Program::default());
+        assert_eq!(output[0].tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+    }
+}
@@ -2,15 +2,33 @@ use super::utils::{self, StdCompletionKind};
 use lsp_types::{Hover, HoverContents};
 use yy_boss::YypBoss;
 
-pub fn hover_on_word(word: &str, gm_manual: &gm_doc::Program, yy_boss: &YypBoss) -> Option<Hover> {
-    const INPUT: [StdCompletionKind; 4] = [
+use url::Url;
+use yy_typings::EventType;
+
+use crate::gml::{Local, SyntaxTree};
+use crate::services::{
+    InstanceVariable, ObjectIndex, ObjectInfo, ResourceLookup, ResourceLookupData, SymbolIndex,
+    SymbolKind,
+};
+
+pub fn hover_on_word(
+    word: &str,
+    gm_manual: &gm_doc::Program,
+    yy_boss: &YypBoss,
+    symbols: &SymbolIndex,
+) -> Option<Hover> {
+    const INPUT: [StdCompletionKind; 8] = [
         StdCompletionKind::Function,
         StdCompletionKind::Variable,
         StdCompletionKind::Constant,
         StdCompletionKind::Object,
+        StdCompletionKind::UserFunction,
+        StdCompletionKind::Enum,
+        StdCompletionKind::GlobalVariable,
+        StdCompletionKind::Macro,
     ];
 
-    utils::detailed_docs_data(word, &INPUT, gm_manual, yy_boss).map(|mut v| {
+    utils::detailed_docs_data(word, &INPUT, gm_manual, yy_boss, symbols).map(|mut v| {
         v.description.insert(
             0,
             lsp_types::MarkedString::from_language_code("gml-gms2".to_string(), v.detail),
@@ -22,3 +40,78 @@ pub fn hover_on_word(word: &str, gm_manual: &gm_doc::Program, yy_boss: &YypBoss)
         }
     })
 }
+
+pub fn hover_on_local(txt: &str, tree: &SyntaxTree, local: &Local<'_>) -> Hover {
+    let mut docs = utils::local_docs_data(txt, tree, local);
+    docs.description.insert(
+        0,
+        lsp_types::MarkedString::from_language_code("gml-gms2".to_string(), docs.detail),
+    );
+
+    Hover {
+        contents: HoverContents::Array(docs.description),
+        range: None,
+    }
+}
+
+pub fn hover_on_instance_variable(variable: &InstanceVariable) -> Hover {
+    let mut docs = utils::instance_variable_docs_data(variable);
+    docs.description.insert(
+        0,
+        lsp_types::MarkedString::from_language_code("gml-gms2".to_string(), docs.detail),
+    );
+
+    Hover {
+        contents: HoverContents::Array(docs.description),
+        range: None,
+    }
+}
+
+/// Hovers over the `member` of `receiver.member`, when `receiver` is an enum.
+pub fn hover_on_member(receiver: &str, member: &str, symbols: &SymbolIndex) -> Option<Hover> {
+    let (symbol, member) = symbols.get(receiver).iter().find_map(|v| match &v.kind {
+        SymbolKind::Enum(decl) => decl.member(member).map(|member| (v, member)),
+        _ => None,
+    })?;
+
+    Some(Hover {
+        contents: HoverContents::Array(vec![
+            lsp_types::MarkedString::from_language_code(
+                "gml-gms2".to_string(),
+                utils::enum_member_detail(receiver, member),
+            ),
+            lsp_types::MarkedString::from_markdown(format!(
+                "Declared in `{}` on line {}",
+                symbol.source,
+                member.range.start.line + 1
+            )),
+        ]),
+        range: None,
+    })
+}
+
+/// Names, and links to, the event which `event_inherited()` will run from `object`'s `event`.
+pub fn describe_event_inherited(
+    object: &ObjectInfo,
+    event: EventType,
+    objects: &ObjectIndex,
+) -> lsp_types::MarkedString {
+    let description = match objects.inherited_event(&object.name, event) {
+        Some((parent, path)) => {
+            let source = ResourceLookup {
+                name: parent.name.clone(),
+                data: ResourceLookupData::Object(event),
+            };
+            match Url::from_file_path(path) {
+                Ok(url) => format!("Runs [{}]({})", source, url),
+                Err(_) => format!("Runs `{}`", source),
+            }
+        }
+        None => format!(
+            "No ancestor of `{}` has this event, so this does nothing",
+            object.name
+        ),
+    };
+
+    lsp_types::MarkedString::from_markdown(description)
+}
@@ -0,0 +1,100 @@
+use crate::{
+    gml::*,
+    services::{EventInfo, InstanceVariable, ObjectIndex, ObjectInfo},
+};
+
+/// Which objects `self` and `other` are instances of at some point in a document. Either is
+/// `None` when we can't tell, like in a script or in a `with` over an arbitrary expression.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstanceContext {
+    pub self_object: Option<String>,
+    pub other_object: Option<String>,
+}
+
+impl InstanceContext {
+    pub fn at(
+        tree: &SyntaxTree,
+        offset: usize,
+        event: Option<(&ObjectInfo, &EventInfo)>,
+        objects: &ObjectIndex,
+    ) -> Self {
+        let mut context = match event {
+            Some((object, event)) => Self {
+                self_object: Some(object.name.clone()),
+                // in a collision event, `other` is whatever we hit
+                other_object: event.collision_object.clone(),
+            },
+            None => Self::default(),
+        };
+
+        // each `with` makes its target `self`, and what was `self` becomes `other`
+        for target in tree.with_targets_at(offset) {
+            let self_object = context.resolve_expr(target, objects);
+            context = Self {
+                self_object,
+                other_object: context.self_object,
+            };
+        }
+
+        context
+    }
+
+    /// Resolves `self`, `other`, or an object's name to an object.
+    pub fn resolve(&self, name: &str, objects: &ObjectIndex) -> Option<String> {
+        match name {
+            "self" => self.self_object.clone(),
+            "other" => self.other_object.clone(),
+            name => objects.get(name).map(|v| v.name.clone()),
+        }
+    }
+
+    fn resolve_expr(&self, expr: &Expr, objects: &ObjectIndex) -> Option<String> {
+        match &expr.kind {
+            ExprKind::Ident(ident) => self.resolve(&ident.name, objects),
+            ExprKind::Grouping(expr) => self.resolve_expr(expr, objects),
+            _ => None,
+        }
+    }
+
+    /// The variables `self` has.
+    pub fn variables<'a>(&self, objects: &'a ObjectIndex) -> Vec<&'a InstanceVariable> {
+        self.self_object
+            .as_ref()
+            .map(|v| objects.variables(v))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yy_typings::EventType;
+
+    #[test]
+    fn with_other_and_self() {
+        let mut objects = ObjectIndex::default();
+        for name in ["obj_player", "obj_enemy", "obj_wall"] {
+            objects.insert(ObjectInfo::new(name.to_string(), []));
+        }
+        let event = EventInfo {
+            event_type: EventType::Collision,
+            path: "Collision.gml".into(),
+            collision_object: Some("obj_wall".to_string()),
+        };
+        let player = objects.get("obj_player").unwrap().clone();
+
+        let txt = "a; with (obj_enemy) { b; with (other) { c; } with (instance_nearest(x, y, all)) { d; } }";
+        let tree = SyntaxTree::parse(txt);
+        let context = |needle| {
+            let offset = txt.find(needle).unwrap();
+            let context = InstanceContext::at(&tree, offset, Some((&player, &event)), &objects);
+            (context.self_object, context.other_object)
+        };
+        let some = |v: &str| Some(v.to_string());
+
+        assert_eq!(context("a;"), (some("obj_player"), some("obj_wall")));
+        assert_eq!(context("b;"), (some("obj_enemy"), some("obj_player")));
+        assert_eq!(context("c;"), (some("obj_player"), some("obj_enemy")));
+        assert_eq!(context("d;"), (None, some("obj_enemy")));
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use url::Url;
+use yy_typings::EventType;
+
+use crate::gml::SyntaxTree;
+use crate::services::{ObjectIndex, SymbolIndex, YyFiles};
+
+/// What the requests which look past the current document, like references and renames, can see
+/// of the project.
+pub struct Project<'a> {
+    pub gm_manual: &'a gm_doc::Program,
+    pub symbols: &'a SymbolIndex,
+    pub objects: &'a ObjectIndex,
+    /// The name of every asset in the project, of every kind.
+    pub assets: &'a BTreeSet<String>,
+    /// Every script and event, with its text and syntax tree.
+    pub documents: BTreeMap<Url, (&'a str, &'a SyntaxTree)>,
+    /// The `.yy` file of each asset by its name, relative to `root_directory` just like the
+    /// `.yyp` records it, like `objects/obj_enemy/obj_enemy.yy`.
+    pub resources: BTreeMap<&'a str, &'a str>,
+    pub root_directory: &'a Path,
+    pub yyp_path: &'a Path,
+    pub yy_files: &'a YyFiles,
+}
+
+impl<'a> Project<'a> {
+    pub fn document(&self, uri: &Url) -> Option<(&'a str, &'a SyntaxTree)> {
+        self.documents.get(uri).copied()
+    }
+
+    /// The `.yy` file of every asset in the project.
+    pub fn yy_paths(&self) -> Vec<PathBuf> {
+        self.resources
+            .values()
+            .map(|v| self.root_directory.join(v))
+            .collect()
+    }
+
+    /// The files which declare an asset: its `.yy` file, and for an object, its Create event too.
+    pub fn asset_files(&self, name: &str) -> Vec<PathBuf> {
+        let Some(relative_path) = self.resources.get(name) else {
+            return vec![];
+        };
+        let create = self
+            .objects
+            .get(name)
+            .and_then(|v| v.event_path(EventType::Create));
+
+        std::iter::once(self.root_directory.join(relative_path))
+            .chain(create.map(Path::to_path_buf))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::services::{EventInfo, ObjectInfo, ResourceLookup, ResourceLookupData};
+
+    /// A project in `/project`, with gml documents and `.yy` files given by their paths relative
+    /// to it. A document in `scripts` is the script named after its folder, and any other is the
+    /// Create event of the object named after its folder. Each `.yy` is an asset.
+    pub struct TestProject {
+        pub gm_manual: gm_doc::Program,
+        symbols: SymbolIndex,
+        objects: ObjectIndex,
+        assets: BTreeSet<String>,
+        documents: Vec<(Url, String, SyntaxTree)>,
+        resources: Vec<(String, String)>,
+        yy_files: YyFiles,
+    }
+
+    impl TestProject {
+        pub fn new(documents: &[(&str, &str)], yy_files: &[(&str, &str)]) -> Self {
+            let mut symbols = SymbolIndex::default();
+            let mut objects = ObjectIndex::default();
+            let mut parsed = vec![];
+            for (relative_path, txt) in documents {
+                let path = Path::new("/project").join(relative_path);
+                let name = folder_name(relative_path);
+                let data = if relative_path.starts_with("scripts/") {
+                    ResourceLookupData::Script
+                } else {
+                    let mut object = ObjectInfo::new(name.clone(), []);
+                    object.events.push(EventInfo {
+                        event_type: EventType::Create,
+                        path: path.clone(),
+                        collision_object: None,
+                    });
+                    objects.insert(object);
+                    ResourceLookupData::Object(EventType::Create)
+                };
+
+                let lookup = ResourceLookup { name, data };
+                let tree = SyntaxTree::parse(txt);
+                symbols.update_file(&path, &lookup, txt, &tree);
+                objects.update_event(&path, &lookup, txt, &tree);
+                parsed.push((uri(relative_path), txt.to_string(), tree));
+            }
+
+            let resources: Vec<(String, String)> = yy_files
+                .iter()
+                .filter(|(path, _)| path.ends_with(".yy"))
+                .map(|(path, _)| (folder_name(path), path.to_string()))
+                .collect();
+
+            Self {
+                gm_manual: gm_doc::Program::default(),
+                symbols,
+                objects,
+                assets: resources.iter().map(|(name, _)| name.clone()).collect(),
+                documents: parsed,
+                resources,
+                yy_files: yy_files
+                    .iter()
+                    .map(|(path, txt)| (Path::new("/project").join(path), txt.to_string()))
+                    .collect(),
+            }
+        }
+
+        pub fn project(&self) -> Project<'_> {
+            Project {
+                gm_manual: &self.gm_manual,
+                symbols: &self.symbols,
+                objects: &self.objects,
+                assets: &self.assets,
+                documents: self
+                    .documents
+                    .iter()
+                    .map(|(uri, txt, tree)| (uri.clone(), (txt.as_str(), tree)))
+                    .collect(),
+                resources: self
+                    .resources
+                    .iter()
+                    .map(|(name, path)| (name.as_str(), path.as_str()))
+                    .collect(),
+                root_directory: Path::new("/project"),
+                yyp_path: Path::new("/project/project.yyp"),
+                yy_files: &self.yy_files,
+            }
+        }
+    }
+
+    /// The uri of a file, given by its path relative to the project.
+    pub fn uri(relative_path: &str) -> Url {
+        Url::from_file_path(Path::new("/project").join(relative_path)).unwrap()
+    }
+
+    fn folder_name(relative_path: &str) -> String {
+        relative_path
+            .split('/')
+            .nth(1)
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[test]
+    fn asset_files() {
+        let test_project = TestProject::new(
+            &[
+                ("objects/obj_player/Create_0.gml", "hp = 10;"),
+                ("scripts/scr_move/scr_move.gml", "function move() {}"),
+            ],
+            &[
+                ("objects/obj_player/obj_player.yy", "{}"),
+                ("objects/obj_wall/obj_wall.yy", "{}"),
+                ("scripts/scr_move/scr_move.yy", "{}"),
+                ("sprites/spr_player/spr_player.yy", "{}"),
+            ],
+        );
+        let project = test_project.project();
+        let files = |name: &str| -> Vec<String> {
+            project
+                .asset_files(name)
+                .into_iter()
+                .map(|v| v.to_string_lossy().into_owned())
+                .collect()
+        };
+
+        assert_eq!(
+            files("obj_player"),
+            vec![
+                "/project/objects/obj_player/obj_player.yy",
+                "/project/objects/obj_player/Create_0.gml"
+            ]
+        );
+        // an object without a Create event just has its `.yy`
+        assert_eq!(
+            files("obj_wall"),
+            vec!["/project/objects/obj_wall/obj_wall.yy"]
+        );
+        assert_eq!(
+            files("scr_move"),
+            vec!["/project/scripts/scr_move/scr_move.yy"]
+        );
+        assert_eq!(
+            files("spr_player"),
+            vec!["/project/sprites/spr_player/spr_player.yy"]
+        );
+        assert!(files("move").is_empty());
+    }
+}
@@ -0,0 +1,380 @@
+use lsp_types::Location;
+use url::Url;
+
+use super::{definition, project::Project};
+use crate::gml::*;
+use crate::services::SymbolKind;
+
+/// What the cursor is on, when looking for its references.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target<'a> {
+    /// A local, which can only be used from within the function which declares it, given by its
+    /// span, or from the top level of its document when that's `None`. A `var` isn't limited to
+    /// the block it's declared in.
+    Local {
+        name: &'a str,
+        function: Option<Span>,
+    },
+    /// The `member` of `Enum.member`.
+    EnumMember { name: &'a str, member: &'a str },
+    /// Anything which is referred to by name alone: functions, macros, globals, instance
+    /// variables and assets.
+    Name(&'a str),
+}
+
+/// Finds every use of the word at the cursor, in every script and event, and for assets, in
+/// every `.yy` file too.
+pub fn references_at(
+    project: &Project<'_>,
+    uri: &Url,
+    offset: usize,
+    include_declaration: bool,
+) -> Option<Vec<Location>> {
+    let (txt, tree) = project.document(uri)?;
+    let (word, span) = tree.word_at(txt, offset)?;
+    let receiver = tree.receiver_before(txt, span.start);
+
+    let enum_member = receiver.and_then(|receiver| {
+        project
+            .symbols
+            .get(receiver)
+            .iter()
+            .find_map(|v| match &v.kind {
+                SymbolKind::Enum(decl) => decl.member(word).map(|member| (v, member)),
+                _ => None,
+            })
+    });
+    let locals = match receiver {
+        Some(_) => vec![],
+        None if tree.local(word, span.end).is_some() => tree.function_local(word, span.start),
+        None => vec![],
+    };
+
+    let mut locations = vec![];
+    let mut push = |location: Location| {
+        if locations.contains(&location) == false {
+            locations.push(location);
+        }
+    };
+
+    let target = if let Some((symbol, member)) = enum_member {
+        if include_declaration {
+            if let Ok(uri) = Url::from_file_path(&symbol.path) {
+                push(Location::new(uri, member.range.into()));
+            }
+        }
+        Target::EnumMember {
+            name: &symbol.name,
+            member: &member.name,
+        }
+    } else if locals.is_empty() == false {
+        if include_declaration {
+            for local in &locals {
+                push(Location::new(
+                    uri.clone(),
+                    tree.range(txt, local.ident.span).into(),
+                ));
+            }
+        }
+        let target = Target::Local {
+            name: word,
+            function: tree.function_at(span.start).map(|v| v.span),
+        };
+        for span in find_in_gml(tree, target) {
+            push(Location::new(uri.clone(), tree.range(txt, span).into()));
+        }
+
+        return Some(locations);
+    } else {
+        if include_declaration {
+            for location in project
+                .symbols
+                .get(word)
+                .iter()
+                .filter_map(definition::location)
+            {
+                push(location);
+            }
+        }
+        Target::Name(word)
+    };
+
+    for (uri, (txt, tree)) in &project.documents {
+        for span in find_in_gml(tree, target) {
+            push(Location::new(uri.clone(), tree.range(txt, span).into()));
+        }
+    }
+
+    if project.assets.contains(word) && matches!(target, Target::Name(_)) {
+        for path in project.yy_paths() {
+            let (Some(txt), Ok(uri)) = (project.yy_files.get(&path), Url::from_file_path(&path))
+            else {
+                continue;
+            };
+            let line_index = LineIndex::new(txt);
+            for span in find_in_yy(txt, word) {
+                push(Location::new(
+                    uri.clone(),
+                    line_index.range(txt, span).into(),
+                ));
+            }
+        }
+    }
+
+    Some(locations)
+}
+
+/// Finds every use of `target` in a gml document.
+pub fn find_in_gml(tree: &SyntaxTree, target: Target<'_>) -> Vec<Span> {
+    let mut finder = ReferenceFinder {
+        tree,
+        target,
+        function: None,
+        locals: tree
+            .function_locals(None)
+            .into_iter()
+            .map(|v| v.ident)
+            .collect(),
+        found: vec![],
+    };
+    for stmt in &tree.stmts {
+        finder.visit_stmt(stmt);
+    }
+
+    finder.found
+}
+
+/// Finds every reference to an asset in a `.yy` file. Other resources point at an asset with
+/// `{"name":"spr_player","path":"sprites/spr_player/spr_player.yy",}`, whether that's an
+/// object's `spriteId`, its `parentObjectId`, or the `objectId` of an instance in a room.
+pub fn find_in_yy(txt: &str, name: &str) -> Vec<Span> {
+    let quoted = format!("\"{}\"", name);
+
+    txt.match_indices(&quoted)
+        .filter(|(start, _)| {
+            let before = txt[..*start].trim_end();
+            let after = txt[start + quoted.len()..].trim_start();
+
+            before
+                .strip_suffix(':')
+                .is_some_and(|v| v.trim_end().ends_with("\"name\""))
+                && after
+                    .strip_prefix(',')
+                    .is_some_and(|v| v.trim_start().starts_with("\"path\""))
+        })
+        .map(|(start, _)| Span::new(start + 1, start + quoted.len() - 1))
+        .collect()
+}
+
+struct ReferenceFinder<'a> {
+    tree: &'a SyntaxTree,
+    target: Target<'a>,
+    /// The function we're in, and the locals it declares.
+    function: Option<Span>,
+    locals: Vec<&'a Ident>,
+    found: Vec<Span>,
+}
+
+impl ReferenceFinder<'_> {
+    /// Checks a name which is read or written on its own, rather than as a field of something.
+    fn bare_name(&mut self, ident: &Ident) {
+        // the same as `SyntaxTree::local`: a local can be used anywhere in its function after
+        // it's been declared
+        let is_local = self
+            .locals
+            .iter()
+            .any(|v| v.name == ident.name && v.span.start < ident.span.start);
+        let found = match self.target {
+            Target::Local { name, function } => {
+                ident.name == name && is_local && self.function == function
+            }
+            Target::Name(name) => ident.name == name && is_local == false,
+            Target::EnumMember { .. } => false,
+        };
+
+        if found {
+            self.found.push(ident.span);
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for ReferenceFinder<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        if let StmtKind::GlobalVar(idents) = &stmt.kind {
+            for ident in idents {
+                self.bare_name(ident);
+            }
+        }
+
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match &expr.kind {
+            ExprKind::Ident(ident) => self.bare_name(ident),
+            // the shorthand `{ a }` reads `a`
+            ExprKind::Struct(fields) => {
+                for field in fields.iter().filter(|v| v.value.is_none()) {
+                    self.bare_name(&field.name);
+                }
+            }
+            ExprKind::Field {
+                expr: receiver,
+                field,
+            } => match self.target {
+                Target::EnumMember { name, member } => {
+                    let on_enum = matches!(&receiver.kind, ExprKind::Ident(v) if v.name == name);
+                    if on_enum && field.name == member {
+                        self.found.push(field.span);
+                    }
+                }
+                // `global.score` and `other.hp` both name what they refer to
+                Target::Name(name) if field.name == name => self.found.push(field.span),
+                _ => {}
+            },
+            _ => {}
+        }
+
+        walk_expr(self, expr);
+    }
+
+    fn visit_function(&mut self, func: &'a FunctionDecl) {
+        let function = self.function.replace(func.span);
+        let locals = std::mem::take(&mut self.locals);
+        self.locals = self
+            .tree
+            .function_locals(Some(func))
+            .into_iter()
+            .map(|v| v.ident)
+            .collect();
+
+        walk_function(self, func);
+
+        self.function = function;
+        self.locals = locals;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intellisense::project::tests::{uri, TestProject};
+
+    fn found<'a>(txt: &'a str, target: Target<'_>) -> Vec<&'a str> {
+        find_in_gml(&SyntaxTree::parse(txt), target)
+            .into_iter()
+            .map(|v| &txt[v.start..v.end])
+            .collect()
+    }
+
+    fn offsets(txt: &str, target: Target<'_>) -> Vec<usize> {
+        find_in_gml(&SyntaxTree::parse(txt), target)
+            .into_iter()
+            .map(|v| v.start)
+            .collect()
+    }
+
+    #[test]
+    fn gml_references() {
+        let txt = "hp = 10;\nother.hp -= 1;\nvar s = { hp };\nfunction f(hp) { return hp; }";
+        assert_eq!(offsets(txt, Target::Name("hp")), vec![0, 15, 34]);
+
+        let txt = "var a = 1;\nfunction f() { var a = 2; return a; }\nshow_debug_message(a);";
+        let target = Target::Local {
+            name: "a",
+            function: None,
+        };
+        assert_eq!(offsets(txt, target), vec![txt.rfind('a').unwrap()]);
+
+        // a `var` outlives the block it's declared in
+        let txt = "if (c) { var a = 1; }\nf(a);\na += 1;";
+        assert_eq!(offsets(txt, target), vec![24, 28]);
+        assert!(found(txt, Target::Name("a")).is_empty());
+
+        let txt = "globalvar score;\nglobal.score = 0;\nscore += 1;";
+        assert_eq!(
+            found(txt, Target::Name("score")),
+            vec!["score", "score", "score"]
+        );
+
+        let txt = "var a = Dir.left;\nvar b = Other.left;\nleft = Dir.right;";
+        let target = Target::EnumMember {
+            name: "Dir",
+            member: "left",
+        };
+        assert_eq!(offsets(txt, target), vec![12]);
+    }
+
+    #[test]
+    fn yy_references() {
+        let txt = r#"{
+  "name": "obj_enemy",
+  "spriteId": {"name":"spr_enemy","path":"sprites/spr_enemy/spr_enemy.yy",},
+  "parentObjectId": {
+    "name": "obj_player",
+    "path": "objects/obj_player/obj_player.yy",
+  },
+  "tags": ["obj_player"],
+}"#;
+        let spans = find_in_yy(txt, "obj_player");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(&txt[spans[0].start..spans[0].end], "obj_player");
+
+        assert_eq!(find_in_yy(txt, "spr_enemy").len(), 1);
+        assert!(find_in_yy(txt, "obj_enemy").is_empty());
+    }
+
+    #[test]
+    fn project_references() {
+        let script = "enum Dir { left, right }\nfunction spawn(count) {\n    var n = count;\n    return n;\n}";
+        let create = "var n = spawn(2);\nfacing = Dir.left;\nsprite_index = spr_enemy;";
+        let test_project = TestProject::new(
+            &[
+                ("scripts/scr_enemy/scr_enemy.gml", script),
+                ("objects/obj_enemy/Create_0.gml", create),
+            ],
+            &[
+                ("scripts/scr_enemy/scr_enemy.yy", r#"{"name":"scr_enemy",}"#),
+                (
+                    "objects/obj_enemy/obj_enemy.yy",
+                    r#"{"name":"obj_enemy","spriteId":{"name":"spr_enemy","path":"sprites/spr_enemy/spr_enemy.yy",},}"#,
+                ),
+                ("sprites/spr_enemy/spr_enemy.yy", r#"{"name":"spr_enemy",}"#),
+            ],
+        );
+        let project = test_project.project();
+        let references = |path: &str, offset: usize| -> Vec<(String, u32, u32)> {
+            let mut output: Vec<_> = references_at(&project, &uri(path), offset, true)
+                .unwrap()
+                .into_iter()
+                .map(|v| {
+                    let path = v.uri.path().trim_start_matches("/project/").to_string();
+                    (path, v.range.start.line, v.range.start.character)
+                })
+                .collect();
+            output.sort();
+            output
+        };
+        let at = |path: &str, line: u32, character: u32| (path.to_string(), line, character);
+        let scr = "scripts/scr_enemy/scr_enemy.gml";
+        let obj = "objects/obj_enemy/Create_0.gml";
+
+        // the `n` in `spawn` isn't the `n` in the Create event
+        assert_eq!(
+            references(scr, script.rfind('n').unwrap()),
+            vec![at(scr, 2, 8), at(scr, 3, 11)]
+        );
+        assert_eq!(
+            references(obj, create.find("spawn").unwrap()),
+            vec![at(obj, 0, 8), at(scr, 1, 9)]
+        );
+        assert_eq!(
+            references(obj, create.find("left").unwrap()),
+            vec![at(obj, 1, 13), at(scr, 0, 11)]
+        );
+        assert_eq!(
+            references(obj, create.find("spr_enemy").unwrap()),
+            vec![at(obj, 2, 15), at("objects/obj_enemy/obj_enemy.yy", 0, 40)]
+        );
+    }
+}
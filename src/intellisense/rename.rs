@@ -0,0 +1,544 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use lsp_types::{
+    ClientCapabilities, DocumentChangeOperation, DocumentChanges, OneOf,
+    OptionalVersionedTextDocumentIdentifier, PrepareRenameResponse, RenameFile, ResourceOp,
+    ResourceOperationKind, TextDocumentEdit, TextEdit, WorkspaceEdit,
+};
+use url::Url;
+
+use super::{project::Project, references};
+use crate::gml::{LineIndex, Span};
+use crate::services::SymbolKind;
+
+/// Whether `name` can be used as an identifier, and so whether anything can be renamed to it.
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|v| v.is_ascii_alphabetic() || v == '_')
+        && chars.all(|v| v.is_ascii_alphanumeric() || v == '_')
+}
+
+/// Checks that the word at the cursor is something of the user's, which can be renamed.
+pub fn prepare_rename_at(
+    project: &Project<'_>,
+    uri: &Url,
+    offset: usize,
+) -> Option<PrepareRenameResponse> {
+    let (txt, tree) = project.document(uri)?;
+    let (word, span) = tree.word_at(txt, offset)?;
+    let receiver = tree.receiver_before(txt, span.start);
+
+    let is_local = receiver.is_none() && tree.local(word, span.end).is_some();
+    let is_enum_member = receiver.is_some_and(|receiver| {
+        project.symbols.get(receiver).iter().any(|v| match &v.kind {
+            SymbolKind::Enum(decl) => decl.member(word).is_some(),
+            _ => false,
+        })
+    });
+    let is_builtin = project.gm_manual.functions.contains_key(word)
+        || project.gm_manual.variables.contains_key(word)
+        || project.gm_manual.constants.contains_key(word);
+    let is_declared = project.symbols.get(word).is_empty() == false
+        || project.assets.contains(word)
+        || project.objects.any_declares(word);
+
+    let renamable = is_local || is_enum_member || (is_declared && is_builtin == false);
+    renamable.then(|| PrepareRenameResponse::Range(tree.range(txt, span).into()))
+}
+
+/// Renames the word at the cursor everywhere it's used. When it's an asset, the asset itself is
+/// renamed too, along with its files.
+pub fn rename_at(
+    project: &Project<'_>,
+    uri: &Url,
+    offset: usize,
+    new_name: &str,
+) -> Option<Rename> {
+    prepare_rename_at(project, uri, offset)?;
+    let (txt, tree) = project.document(uri)?;
+    let (word, span) = tree.word_at(txt, offset)?;
+
+    let mut edits: BTreeMap<Url, Vec<TextEdit>> = BTreeMap::new();
+    for location in references::references_at(project, uri, offset, true)? {
+        edits
+            .entry(location.uri)
+            .or_default()
+            .push(TextEdit::new(location.range, new_name.to_string()));
+    }
+
+    let is_asset = project.assets.contains(word)
+        && tree.receiver_before(txt, span.start).is_none()
+        && tree.local(word, span.end).is_none();
+    let relative_path = project.resources.get(word).filter(|_| is_asset);
+    let Some(relative_path) = relative_path else {
+        return Some(Rename {
+            edits,
+            operations: vec![],
+            asset: None,
+            new_name: new_name.to_string(),
+        });
+    };
+
+    let yy_path = project.root_directory.join(relative_path);
+    let asset = AssetRename {
+        name: word,
+        new_name,
+        yy_path: &yy_path,
+        relative_path,
+    };
+
+    let yyp_path = project.yyp_path.to_path_buf();
+    for path in project.yy_paths().into_iter().chain([yyp_path]) {
+        let (Some(txt), Ok(uri)) = (project.yy_files.get(&path), Url::from_file_path(&path)) else {
+            continue;
+        };
+        edits
+            .entry(uri)
+            .or_default()
+            .extend(asset.text_edits(&path, txt));
+    }
+
+    let files: Vec<_> = yy_path
+        .parent()
+        .and_then(|v| v.read_dir().ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.ok().map(|v| v.path()))
+        .collect();
+    let operations = asset.file_operations(&files);
+
+    Some(Rename {
+        edits,
+        operations,
+        asset: Some(word.to_string()),
+        new_name: new_name.to_string(),
+    })
+}
+
+/// What the client lets us do when we edit the workspace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EditCapabilities {
+    /// Whether an edit can be a list of `documentChanges`, rather than just a map of `changes`.
+    pub document_changes: bool,
+    /// Whether those `documentChanges` can rename files, which renaming an asset needs.
+    pub rename_files: bool,
+}
+
+impl EditCapabilities {
+    pub fn new(capabilities: &ClientCapabilities) -> Self {
+        let Some(workspace) = &capabilities.workspace else {
+            return Self::default();
+        };
+        let edit = workspace.workspace_edit.as_ref();
+        let document_changes = edit.and_then(|v| v.document_changes) == Some(true);
+
+        Self {
+            document_changes,
+            rename_files: document_changes
+                && edit
+                    .and_then(|v| v.resource_operations.as_ref())
+                    .is_some_and(|v| v.contains(&ResourceOperationKind::Rename)),
+        }
+    }
+}
+
+/// A rename which is ready to send to the client.
+pub struct Rename {
+    pub edits: BTreeMap<Url, Vec<TextEdit>>,
+    pub operations: Vec<ResourceOp>,
+    /// The asset which is renamed along with its files, if it is one.
+    pub asset: Option<String>,
+    pub new_name: String,
+}
+
+/// An asset which is being renamed, along with where it lives.
+pub struct AssetRename<'a> {
+    pub name: &'a str,
+    pub new_name: &'a str,
+    /// The asset's `.yy` file.
+    pub yy_path: &'a Path,
+    /// The path of the asset's `.yy` relative to the project, like
+    /// `objects/obj_enemy/obj_enemy.yy`, which is how the `.yyp` and other resources refer to it.
+    pub relative_path: &'a str,
+}
+
+impl AssetRename<'_> {
+    /// The edits to a `.yy` or `.yyp` file: every reference to the asset, by name and by path,
+    /// and in the asset's own `.yy`, its name.
+    pub fn text_edits(&self, path: &Path, txt: &str) -> Vec<TextEdit> {
+        let line_index = LineIndex::new(txt);
+        let mut edits = vec![];
+
+        let mut names = references::find_in_yy(txt, self.name);
+        if path == self.yy_path {
+            names.extend(own_names(txt, self.name));
+        }
+        for span in names {
+            edits.push(TextEdit::new(
+                line_index.range(txt, span).into(),
+                self.new_name.to_string(),
+            ));
+        }
+
+        let quoted = format!("\"{}\"", self.relative_path);
+        for (start, _) in txt.match_indices(&quoted) {
+            let span = Span::new(start + 1, start + quoted.len() - 1);
+            edits.push(TextEdit::new(
+                line_index.range(txt, span).into(),
+                self.new_relative_path(),
+            ));
+        }
+
+        edits
+    }
+
+    /// Moves every file in the asset's folder which is named after it, like its `.yy` or a
+    /// script's `.gml`, and then the folder itself.
+    pub fn file_operations(&self, files: &[PathBuf]) -> Vec<ResourceOp> {
+        let mut operations = vec![];
+        for file in files {
+            if file.file_stem().is_some_and(|v| v == self.name) == false {
+                continue;
+            }
+            let mut new_file = file.with_file_name(self.new_name);
+            if let Some(extension) = file.extension() {
+                new_file.set_extension(extension);
+            }
+            operations.extend(rename_file(file, &new_file));
+        }
+
+        if let Some(folder) = self.yy_path.parent() {
+            operations.extend(rename_file(folder, &folder.with_file_name(self.new_name)));
+        }
+
+        operations
+    }
+
+    fn new_relative_path(&self) -> String {
+        let kind = self.relative_path.split('/').next().unwrap_or_default();
+        format!("{}/{}/{}.yy", kind, self.new_name, self.new_name)
+    }
+}
+
+/// Where a `.yy` gives its own name, which is `"name"` in older formats and `"%Name"` in newer
+/// ones.
+fn own_names(txt: &str, name: &str) -> Vec<Span> {
+    let quoted = format!("\"{}\"", name);
+
+    txt.match_indices(&quoted)
+        .filter(|(start, _)| {
+            txt[..*start]
+                .trim_end()
+                .strip_suffix(':')
+                .map(str::trim_end)
+                .is_some_and(|v| v.ends_with("\"name\"") || v.ends_with("\"%Name\""))
+        })
+        .map(|(start, _)| Span::new(start + 1, start + quoted.len() - 1))
+        .collect()
+}
+
+fn rename_file(from: &Path, to: &Path) -> Option<ResourceOp> {
+    Some(ResourceOp::Rename(RenameFile {
+        old_uri: Url::from_file_path(from).ok()?,
+        new_uri: Url::from_file_path(to).ok()?,
+        options: None,
+        annotation_id: None,
+    }))
+}
+
+impl Rename {
+    /// Puts the rename together into one edit. The text edits come first, since they're made
+    /// against the files where they are now, and then the files are moved. A client without
+    /// `documentChanges` just gets a map of the text edits, since it can't move files anyway.
+    pub fn workspace_edit(&self, document_changes: bool) -> WorkspaceEdit {
+        let edits = self.edits.iter().map(|(uri, edits)| {
+            let mut edits = edits.clone();
+            edits.sort_by_key(|v| (v.range.start.line, v.range.start.character));
+            edits.dedup_by(|a, b| a.range == b.range);
+            (uri.clone(), edits)
+        });
+
+        if document_changes == false {
+            return WorkspaceEdit::new(edits.collect::<HashMap<_, _>>());
+        }
+
+        let mut changes = vec![];
+        for (uri, edits) in edits {
+            changes.push(DocumentChangeOperation::Edit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                edits: edits.into_iter().map(OneOf::Left).collect(),
+            }));
+        }
+        changes.extend(
+            self.operations
+                .iter()
+                .cloned()
+                .map(DocumentChangeOperation::Op),
+        );
+
+        WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Operations(changes)),
+            ..WorkspaceEdit::default()
+        }
+    }
+
+    /// Where a file ends up once the rename has moved the asset's files, which is where it
+    /// already is for anything outside of the asset's folder.
+    pub fn moved(&self, uri: &Url) -> Url {
+        let mut uri = uri.clone();
+        for operation in &self.operations {
+            let ResourceOp::Rename(v) = operation else {
+                continue;
+            };
+            let (Ok(path), Ok(from), Ok(to)) = (
+                uri.to_file_path(),
+                v.old_uri.to_file_path(),
+                v.new_uri.to_file_path(),
+            ) else {
+                continue;
+            };
+            let new_path = match path.strip_prefix(&from) {
+                Ok(rest) if rest.as_os_str().is_empty() => to,
+                Ok(rest) => to.join(rest),
+                Err(_) => continue,
+            };
+            if let Ok(new_uri) = Url::from_file_path(new_path) {
+                uri = new_uri;
+            }
+        }
+
+        uri
+    }
+}
+
+/// Makes a document's edits to its text, as the client will.
+pub fn apply_edits(txt: &str, edits: &[TextEdit]) -> String {
+    let line_index = LineIndex::new(txt);
+    let mut edits: Vec<(usize, usize, &str)> = edits
+        .iter()
+        .filter_map(|v| {
+            let start = line_index.offset(txt, v.range.start.into())?;
+            let end = line_index.offset(txt, v.range.end.into())?;
+            Some((start, end, v.new_text.as_str()))
+        })
+        .collect();
+    edits.sort();
+    edits.dedup();
+
+    let mut output = txt.to_string();
+    for (start, end, new_text) in edits.into_iter().rev() {
+        output.replace_range(start..end, new_text);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intellisense::diagnostics::tests::manual;
+    use crate::intellisense::project::tests::{uri, TestProject};
+
+    #[test]
+    fn identifiers() {
+        assert!(is_identifier("obj_boss"));
+        assert!(is_identifier("_private2"));
+        assert!(is_identifier("2fast") == false);
+        assert!(is_identifier("has space") == false);
+        assert!(is_identifier("") == false);
+    }
+
+    #[test]
+    fn asset_rename() {
+        let rename = AssetRename {
+            name: "obj_enemy",
+            new_name: "obj_boss",
+            yy_path: Path::new("/project/objects/obj_enemy/obj_enemy.yy"),
+            relative_path: "objects/obj_enemy/obj_enemy.yy",
+        };
+        let new_text = |path: &str, txt: &str| -> String {
+            apply_edits(txt, &rename.text_edits(Path::new(path), txt))
+        };
+
+        assert_eq!(
+            new_text(
+                "/project/objects/obj_enemy/obj_enemy.yy",
+                r#"{"%Name":"obj_enemy","name":"obj_enemy","spriteId":null,}"#
+            ),
+            r#"{"%Name":"obj_boss","name":"obj_boss","spriteId":null,}"#
+        );
+        assert_eq!(
+            new_text(
+                "/project/rooms/rm_level/rm_level.yy",
+                r#"{"objectId":{"name":"obj_enemy","path":"objects/obj_enemy/obj_enemy.yy",},"name":"obj_enemy",}"#
+            ),
+            r#"{"objectId":{"name":"obj_boss","path":"objects/obj_boss/obj_boss.yy",},"name":"obj_enemy",}"#
+        );
+
+        let operations = rename.file_operations(&[
+            PathBuf::from("/project/objects/obj_enemy/obj_enemy.yy"),
+            PathBuf::from("/project/objects/obj_enemy/Create_0.gml"),
+        ]);
+        let moves: Vec<(String, String)> = operations
+            .into_iter()
+            .map(|v| match v {
+                ResourceOp::Rename(v) => {
+                    (v.old_uri.path().to_string(), v.new_uri.path().to_string())
+                }
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            moves,
+            vec![
+                (
+                    "/project/objects/obj_enemy/obj_enemy.yy".to_string(),
+                    "/project/objects/obj_enemy/obj_boss.yy".to_string()
+                ),
+                (
+                    "/project/objects/obj_enemy".to_string(),
+                    "/project/objects/obj_boss".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn edit_capabilities() {
+        let capabilities: ClientCapabilities = serde_json::from_str(
+            r#"{"workspace":{"applyEdit":true,"workspaceEdit":{"documentChanges":true,"resourceOperations":["create","delete"]}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            EditCapabilities::new(&capabilities),
+            EditCapabilities {
+                document_changes: true,
+                rename_files: false,
+            }
+        );
+        assert_eq!(
+            EditCapabilities::new(&ClientCapabilities::default()),
+            EditCapabilities::default()
+        );
+
+        let uri = Url::parse("file:///project/scripts/scr_a/scr_a.gml").unwrap();
+        let edit = TextEdit::new(Default::default(), "b".to_string());
+        let rename = Rename {
+            edits: BTreeMap::from([(uri.clone(), vec![edit.clone(), edit.clone()])]),
+            operations: vec![],
+            asset: None,
+            new_name: "b".to_string(),
+        };
+        assert_eq!(
+            rename.workspace_edit(false),
+            WorkspaceEdit::new(HashMap::from([(uri, vec![edit])]))
+        );
+    }
+
+    #[test]
+    fn renames() {
+        let script = "enum Dir { left, right }\nfunction spawn(count) {\n    var n = count;\n    return n;\n}";
+        let create = "hp = 10;\nfacing = Dir.left;\nvar o = instance_create_depth(0, 0, 0, obj_enemy);\nshow_debug_message(hp + undeclared);";
+        let yy = r#"{"%Name":"obj_enemy","name":"obj_enemy",}"#;
+        let yyp = r#"{"resources":[{"id":{"name":"obj_enemy","path":"objects/obj_enemy/obj_enemy.yy",},},],}"#;
+        let mut test_project = TestProject::new(
+            &[
+                ("scripts/scr_enemy/scr_enemy.gml", script),
+                ("objects/obj_enemy/Create_0.gml", create),
+            ],
+            &[("objects/obj_enemy/obj_enemy.yy", yy), ("project.yyp", yyp)],
+        );
+        test_project.gm_manual = manual(&[
+            ("show_debug_message", &[("value", "Any", false)]),
+            ("instance_create_depth", &[]),
+        ]);
+        let project = test_project.project();
+        let scr = uri("scripts/scr_enemy/scr_enemy.gml");
+        let obj = uri("objects/obj_enemy/Create_0.gml");
+
+        let renamable = |uri: &Url, txt: &str, word: &str| {
+            prepare_rename_at(&project, uri, txt.find(word).unwrap()).is_some()
+        };
+        assert!(renamable(&obj, create, "left"));
+        assert!(renamable(&scr, script, "spawn"));
+        assert!(renamable(&scr, script, "count"));
+        assert!(renamable(&obj, create, "hp"));
+        assert!(renamable(&obj, create, "obj_enemy"));
+        // builtins, names which aren't declared anywhere, and anything that isn't a name
+        assert!(renamable(&obj, create, "show_debug_message") == false);
+        assert!(renamable(&obj, create, "undeclared") == false);
+        assert!(renamable(&obj, create, "10") == false);
+        assert!(rename_at(&project, &obj, create.find("undeclared").unwrap(), "a").is_none());
+
+        let new_texts = |rename: &Rename| -> BTreeMap<String, String> {
+            rename
+                .edits
+                .iter()
+                .map(|(uri, edits)| {
+                    let path = uri.path().trim_start_matches("/project/").to_string();
+                    let txt = match project.document(uri) {
+                        Some((txt, _)) => txt,
+                        None => project.yy_files.get(&uri.to_file_path().unwrap()).unwrap(),
+                    };
+                    (path, apply_edits(txt, edits))
+                })
+                .collect()
+        };
+
+        let rename = rename_at(&project, &obj, create.find("left").unwrap(), "west").unwrap();
+        assert!(rename.asset.is_none());
+        assert!(rename.operations.is_empty());
+        assert_eq!(
+            new_texts(&rename),
+            BTreeMap::from([
+                (
+                    "objects/obj_enemy/Create_0.gml".to_string(),
+                    create.replace("Dir.left", "Dir.west")
+                ),
+                (
+                    "scripts/scr_enemy/scr_enemy.gml".to_string(),
+                    script.replace("left", "west")
+                ),
+            ])
+        );
+
+        let rename =
+            rename_at(&project, &obj, create.find("obj_enemy").unwrap(), "obj_foe").unwrap();
+        assert_eq!(rename.asset.as_deref(), Some("obj_enemy"));
+        assert_eq!(
+            new_texts(&rename),
+            BTreeMap::from([
+                (
+                    "objects/obj_enemy/Create_0.gml".to_string(),
+                    create.replace("obj_enemy", "obj_foe")
+                ),
+                (
+                    "objects/obj_enemy/obj_enemy.yy".to_string(),
+                    yy.replace("obj_enemy", "obj_foe")
+                ),
+                (
+                    "project.yyp".to_string(),
+                    yyp.replace("obj_enemy", "obj_foe")
+                ),
+            ])
+        );
+        let moves: Vec<(&str, &str)> = rename
+            .operations
+            .iter()
+            .map(|v| match v {
+                ResourceOp::Rename(v) => (v.old_uri.path(), v.new_uri.path()),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            moves,
+            vec![("/project/objects/obj_enemy", "/project/objects/obj_foe")]
+        );
+        assert_eq!(rename.moved(&obj), uri("objects/obj_foe/Create_0.gml"));
+        assert_eq!(rename.moved(&scr), scr);
+    }
+}
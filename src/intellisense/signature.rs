@@ -1,183 +1,207 @@
-use std::{
-    iter::{Rev, Skip},
-    str::Chars,
-};
-
 use itertools::Itertools;
-use lsp_types::{Documentation, MarkupContent, SignatureHelp};
+use lsp_types::{
+    Documentation, MarkupContent, ParameterInformation, SignatureHelp, SignatureInformation,
+};
 
-use crate::lsp::Position;
+use crate::{
+    gml::SyntaxTree,
+    lsp::Position,
+    services::{FunctionSymbol, SymbolIndex, SymbolKind},
+};
 
 pub fn signature_help(
     document: &str,
+    tree: &SyntaxTree,
     position: Position,
     gm_manual: &gm_doc::Program,
+    symbols: &SymbolIndex,
 ) -> Option<SignatureHelp> {
-    func_name_and_param(document, position)
-        .and_then(|(name, active_parameter)| {
-            gm_manual.functions.get(&name).map(|func| {
-                // compose signature:
-                let label = format!(
-                    "{}({}): {}",
-                    func.name,
-                    func.parameters.iter().map(|v| &v.name).format(", "),
-                    func.returns
-                );
-
-                // gather parameters:
-                let parameters = func
-                    .parameters
-                    .iter()
-                    .map(|p| lsp_types::ParameterInformation {
-                        label: lsp_types::ParameterLabel::Simple(p.name.to_string()),
-                        documentation: Some(Documentation::MarkupContent(MarkupContent {
-                            kind: lsp_types::MarkupKind::Markdown,
-                            value: p.description.to_string(),
-                        })),
-                    })
-                    .collect();
-
-                lsp_types::SignatureInformation {
-                    label,
-                    documentation: Some(Documentation::MarkupContent(MarkupContent {
-                        kind: lsp_types::MarkupKind::Markdown,
-                        value: func.description.clone(),
-                    })),
-                    parameters: Some(parameters),
-                    active_parameter: Some(active_parameter as u32),
-                }
-            })
-        })
-        .map(|signature_information| SignatureHelp {
-            active_parameter: signature_information.active_parameter,
-            signatures: vec![signature_information],
-            active_signature: Some(0),
-        })
+    let (name, active_parameter, is_new) = func_name_and_param(document, tree, position)?;
+
+    let builtin = gm_manual.functions.get(&name).filter(|_| is_new == false);
+    let mut signature_information = match builtin {
+        Some(func) => builtin_signature(func),
+        None => {
+            let functions = symbols
+                .get(&name)
+                .iter()
+                .filter_map(|symbol| match &symbol.kind {
+                    SymbolKind::Function(func) => Some((symbol, func)),
+                    _ => None,
+                });
+
+            // `new` is only for constructors, but it's easy enough to call one without it
+            let (symbol, func) = functions
+                .clone()
+                .find(|(_, func)| func.constructor == is_new)
+                .or_else(|| functions.clone().next())?;
+            user_signature(&symbol.name, func)
+        }
+    };
+    signature_information.active_parameter =
+        Some(clamp_variadic(&signature_information, active_parameter) as u32);
+
+    Some(SignatureHelp {
+        active_parameter: signature_information.active_parameter,
+        signatures: vec![signature_information],
+        active_signature: Some(0),
+    })
 }
 
-fn func_name_and_param(document: &str, position: Position) -> Option<(String, usize)> {
-    get_pos_in_document(document, position).and_then(|pos| {
-        log::info!("we're in the document...");
-        let mut iter = SignatureIterator::new(document, pos);
-
-        iter.eat_parameters().and_then(|count| {
-            log::info!("parameters have been eaten");
-            iter.eat_identifier().map(|ident| (ident, count))
+fn builtin_signature(func: &gm_doc::Function) -> SignatureInformation {
+    // compose signature:
+    let label = format!(
+        "{}({}): {}",
+        func.name,
+        func.parameters
+            .iter()
+            .map(|v| param_label(&v.name, Some(&v.gm_type), None, v.optional))
+            .format(", "),
+        func.returns
+    );
+
+    // gather parameters:
+    let parameters = func
+        .parameters
+        .iter()
+        .map(|p| {
+            let label = param_label(&p.name, Some(&p.gm_type), None, p.optional);
+            parameter(label, &p.description)
         })
-    })
+        .collect();
+
+    SignatureInformation {
+        label,
+        documentation: Some(markdown(&func.description)),
+        parameters: Some(parameters),
+        active_parameter: None,
+    }
 }
 
-pub fn get_pos_in_document(document: &str, pos: Position) -> Option<usize> {
-    let mut lines_to_go = pos.line;
-    let mut offset = if lines_to_go == 0 { Some(0) } else { None };
+/// Functions declared in the project. Parameter names and defaults come from the declaration and
+/// their docs come from JSDoc. Functions which use `argument0` and friends rather than declaring
+/// parameters get their names from JSDoc too.
+fn user_signature(name: &str, func: &FunctionSymbol) -> SignatureInformation {
+    let docs = &func.docs;
+    let params: Vec<(&str, String)> = if func.params.is_empty() {
+        docs.params
+            .iter()
+            .map(|v| {
+                let label = param_label(&v.name, v.gm_type.as_deref(), None, v.optional);
+                (v.name.as_str(), label)
+            })
+            .collect()
+    } else {
+        func.params
+            .iter()
+            .map(|v| {
+                let jsdoc = docs.param(&v.name);
+                let gm_type = jsdoc.and_then(|v| v.gm_type.as_deref());
+                let optional = v.default.is_some() || jsdoc.is_some_and(|v| v.optional);
+                let label = param_label(&v.name, gm_type, v.default.as_deref(), optional);
+                (v.name.as_str(), label)
+            })
+            .collect()
+    };
+
+    let new = if func.constructor { "new " } else { "" };
+    let mut label = format!(
+        "{}{}({})",
+        new,
+        name,
+        params.iter().map(|(_, label)| label).format(", ")
+    );
+    if let Some(gm_type) = docs.returns.as_ref().and_then(|v| v.gm_type.as_ref()) {
+        label = format!("{}: {}", label, gm_type);
+    }
 
-    for (i, chr) in document.char_indices() {
-        if let Some(offset) = offset {
-            if i - offset == pos.column {
-                return Some(i);
-            }
-        }
+    let parameters = params
+        .into_iter()
+        .map(|(name, label)| {
+            let description = docs
+                .param(name)
+                .map(|v| v.description.as_str())
+                .unwrap_or_default();
+            parameter(label, description)
+        })
+        .collect();
 
-        if chr == '\n' {
-            if lines_to_go == 0 {
-                return None;
-            } else {
-                lines_to_go -= 1;
-                if lines_to_go == 0 {
-                    offset = Some(i + 1);
-                }
-            }
-        }
+    SignatureInformation {
+        label,
+        documentation: Some(markdown(&docs.description)),
+        parameters: Some(parameters),
+        active_parameter: None,
     }
-
-    None
 }
 
-struct SignatureIterator<'a> {
-    iter: Skip<Rev<Chars<'a>>>,
-}
+/// Renders a parameter like `x: Real`, or `[count: Real = 1]` when it can be left out.
+fn param_label(name: &str, gm_type: Option<&str>, default: Option<&str>, optional: bool) -> String {
+    let mut label = name.to_string();
+    if let Some(gm_type) = gm_type.filter(|v| v.is_empty() == false) {
+        label = format!("{}: {}", label, gm_type);
+    }
+    if let Some(default) = default {
+        label = format!("{} = {}", label, default);
+    }
 
-impl<'a> SignatureIterator<'a> {
-    pub fn new(data: &'a str, start: usize) -> Self {
-        Self {
-            iter: data.chars().rev().skip(data.len() - start),
-        }
+    // the manual already writes some optional parameters in brackets
+    if optional && label.starts_with('[') == false {
+        label = format!("[{}]", label);
     }
 
-    pub fn eat_parameters(&mut self) -> Option<usize> {
-        let mut paren_nesting: usize = 0;
-        let mut bracket_nesting: usize = 0;
-        let mut param_count: usize = 0;
-
-        while let Some(n) = self.iter.next() {
-            match n {
-                '(' => {
-                    if paren_nesting == 0 {
-                        return Some(param_count);
-                    } else {
-                        paren_nesting -= 1;
-                    }
-                }
-                ')' => {
-                    paren_nesting += 1;
-                }
-                '[' => {
-                    bracket_nesting = bracket_nesting.saturating_sub(1);
-                }
-                ']' => {
-                    bracket_nesting += 1;
-                }
-                '"' | '\'' => {
-                    // NOMM until we find the other pair
-                    for sub in &mut self.iter {
-                        if n == sub {
-                            break;
-                        }
-                    }
-                }
-                ',' => {
-                    // add the GLORIOUS param count
-                    if paren_nesting == 0 && bracket_nesting == 0 {
-                        param_count += 1;
-                    }
-                }
-                _ => {}
-            }
-        }
+    label
+}
 
-        None
+/// Variadic functions, like `ds_list_add`, end with a `...` parameter which every argument past
+/// the last declared one belongs to.
+fn clamp_variadic(signature: &SignatureInformation, active_parameter: usize) -> usize {
+    let Some(parameters) = &signature.parameters else {
+        return active_parameter;
+    };
+    let variadic = parameters.last().is_some_and(|v| match &v.label {
+        lsp_types::ParameterLabel::Simple(label) => label.contains("..."),
+        lsp_types::ParameterLabel::LabelOffsets(_) => false,
+    });
+
+    if variadic {
+        active_parameter.min(parameters.len() - 1)
+    } else {
+        active_parameter
     }
+}
 
-    pub fn eat_identifier(self) -> Option<String> {
-        let mut ident_started = false;
-        let mut identifier = Vec::new();
-
-        for chr in self.iter {
-            if ident_started == false && chr.is_whitespace() {
-                continue;
-            }
+fn parameter(label: String, description: &str) -> ParameterInformation {
+    ParameterInformation {
+        label: lsp_types::ParameterLabel::Simple(label),
+        documentation: Some(markdown(description)),
+    }
+}
 
-            if chr.is_alphanumeric() || chr == '_' {
-                ident_started = true;
+fn markdown(value: &str) -> Documentation {
+    Documentation::MarkupContent(MarkupContent {
+        kind: lsp_types::MarkupKind::Markdown,
+        value: value.to_string(),
+    })
+}
 
-                identifier.push(chr);
-            } else if ident_started {
-                break;
-            }
-        }
+fn func_name_and_param(
+    document: &str,
+    tree: &SyntaxTree,
+    position: Position,
+) -> Option<(String, usize, bool)> {
+    let offset = tree.offset(document, position)?;
+    let call = tree.call_at(offset)?;
 
-        if identifier.is_empty() {
-            None
-        } else {
-            identifier.reverse();
-            Some(identifier.into_iter().collect())
-        }
-    }
+    call.name()
+        .map(|name| (name.to_string(), call.active_param, call.is_new))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use super::*;
+    use crate::services::{ResourceLookup, ResourceLookupData};
 
     pub fn char_pos_from_string(input: &str) -> (usize, String) {
         let pos = input
@@ -210,84 +234,209 @@ mod tests {
         assert_eq!(position, Position { line: 1, column: 2 });
     }
 
-    #[test]
-    fn signature_iterator() {
-        let (idx, input) = char_pos_from_string("show_debug_message(x,? y);");
-        let input = &input;
-
-        let mut sig = SignatureIterator::new(input, idx);
-        assert_eq!(1, sig.eat_parameters().unwrap());
-        assert_eq!("show_debug_message", sig.eat_identifier().unwrap());
-
-        let mut sig = SignatureIterator::new(input, idx - 1);
-        assert_eq!(0, sig.eat_parameters().unwrap());
-        assert_eq!("show_debug_message", sig.eat_identifier().unwrap());
-
-        let mut sig = SignatureIterator::new(input, idx - 2);
-        assert_eq!(0, sig.eat_parameters().unwrap());
-        assert_eq!("show_debug_message", sig.eat_identifier().unwrap());
-
-        let mut sig = SignatureIterator::new(input, idx - 3);
-        assert!(sig.eat_parameters().is_none());
-        assert!(sig.eat_identifier().is_none());
-    }
-
     #[test]
     fn full() {
         let (idx, input) = char_pos_from_string("show_debug_message(x,? y);");
         let position = Position::new_idx(idx, &input);
 
         assert_eq!(
-            ("show_debug_message".to_string(), 1),
-            func_name_and_param(&input, position).unwrap()
+            ("show_debug_message".to_string(), 1, false),
+            func_name_and_param(&input, &SyntaxTree::parse(&input), position).unwrap()
         );
 
         let (idx, input) = char_pos_from_string("show_debug_message(x?, y);");
         let position = Position::new_idx(idx, &input);
 
         assert_eq!(
-            ("show_debug_message".to_string(), 0),
-            func_name_and_param(&input, position).unwrap()
+            ("show_debug_message".to_string(), 0, false),
+            func_name_and_param(&input, &SyntaxTree::parse(&input), position).unwrap()
         );
 
         let (idx, input) = char_pos_from_string("show_debug_message(x, y?);");
         let position = Position::new_idx(idx, &input);
 
         assert_eq!(
-            ("show_debug_message".to_string(), 1),
-            func_name_and_param(&input, position).unwrap()
+            ("show_debug_message".to_string(), 1, false),
+            func_name_and_param(&input, &SyntaxTree::parse(&input), position).unwrap()
         );
 
         let (idx, input) = char_pos_from_string("show_debug_message(\n?x);");
         let position = Position::new_idx(idx, &input);
 
         assert_eq!(
-            ("show_debug_message".to_string(), 0),
-            func_name_and_param(&input, position).unwrap()
+            ("show_debug_message".to_string(), 0, false),
+            func_name_and_param(&input, &SyntaxTree::parse(&input), position).unwrap()
         );
 
         let (idx, input) = char_pos_from_string("show_debug_message(\n,?x);");
         let position = Position::new_idx(idx, &input);
 
         assert_eq!(
-            ("show_debug_message".to_string(), 1),
-            func_name_and_param(&input, position).unwrap()
+            ("show_debug_message".to_string(), 1, false),
+            func_name_and_param(&input, &SyntaxTree::parse(&input), position).unwrap()
         );
 
         let (idx, input) = char_pos_from_string("show_debug_message(,,?);");
         let position = Position::new_idx(idx, &input);
 
         assert_eq!(
-            ("show_debug_message".to_string(), 2),
-            func_name_and_param(&input, position).unwrap()
+            ("show_debug_message".to_string(), 2, false),
+            func_name_and_param(&input, &SyntaxTree::parse(&input), position).unwrap()
         );
 
         let (idx, input) = char_pos_from_string("warn(\"this is a message, yup {}\"?, y);");
         let position = Position::new_idx(idx, &input);
 
         assert_eq!(
-            ("warn".to_string(), 0),
-            func_name_and_param(&input, position).unwrap()
+            ("warn".to_string(), 0, false),
+            func_name_and_param(&input, &SyntaxTree::parse(&input), position).unwrap()
+        );
+    }
+
+    #[test]
+    fn user_functions() {
+        let txt = r#"
+/// @description Spawns some enemies
+/// @param {Real} x The x position
+/// @param {Real} y The y position
+/// @returns {Array} The spawned enemies
+function scr_spawn(x, y) {}
+
+/// @param {String} message
+function scr_old() {}
+
+/// @param {Real} x The x component
+function Vector(x = 0, y = 0) constructor {}
+"#;
+        let mut symbols = SymbolIndex::default();
+        let source = ResourceLookup {
+            name: "scr_spawn".to_string(),
+            data: ResourceLookupData::Script,
+        };
+        symbols.update_file(
+            Path::new("scr_spawn.gml"),
+            &source,
+            txt,
+            &SyntaxTree::parse(txt),
+        );
+        let function = |name: &str| match &symbols.get(name)[0].kind {
+            SymbolKind::Function(func) => user_signature(name, func),
+            _ => unreachable!(),
+        };
+
+        let spawn = function("scr_spawn");
+        assert_eq!(spawn.label, "scr_spawn(x: Real, y: Real): Array");
+        let parameters = spawn.parameters.unwrap();
+        assert_eq!(parameters.len(), 2);
+        assert_eq!(
+            parameters[1].documentation,
+            Some(markdown("The y position"))
         );
+
+        let old = function("scr_old");
+        assert_eq!(old.label, "scr_old(message: String)");
+
+        let vector = function("Vector");
+        assert_eq!(vector.label, "new Vector([x: Real = 0], [y = 0])");
+        let parameters = vector.parameters.unwrap();
+        assert_eq!(
+            parameters[0].label,
+            lsp_types::ParameterLabel::Simple("[x: Real = 0]".to_string())
+        );
+        assert_eq!(
+            parameters[0].documentation,
+            Some(markdown("The x component"))
+        );
+
+        let (idx, input) = char_pos_from_string("v = new Vector(1,? 2);");
+        let position = Position::new_idx(idx, &input);
+        assert_eq!(
+            ("Vector".to_string(), 1, true),
+            func_name_and_param(&input, &SyntaxTree::parse(&input), position).unwrap()
+        );
+    }
+
+    #[test]
+    fn constructor_calls() {
+        let txt = "function Vector(x, y) constructor {}\nfunction point(x) {}";
+        let mut symbols = SymbolIndex::default();
+        symbols.update_file(
+            Path::new("scr_vector.gml"),
+            &ResourceLookup {
+                name: "scr_vector".to_string(),
+                data: ResourceLookupData::Script,
+            },
+            txt,
+            &SyntaxTree::parse(txt),
+        );
+        let mut gm_manual = gm_doc::Program::default();
+        gm_manual.functions.insert(
+            "point".to_string(),
+            gm_doc::Function {
+                name: "point".to_string(),
+                parameters: vec![],
+                description: String::new(),
+                returns: "Real".to_string(),
+                deprecated: false,
+                pure: false,
+                link: None,
+            },
+        );
+        let label = |input: &str| -> Option<String> {
+            let (idx, input) = char_pos_from_string(input);
+            let position = Position::new_idx(idx, &input);
+            let help = signature_help(
+                &input,
+                &SyntaxTree::parse(&input),
+                position,
+                &gm_manual,
+                &symbols,
+            )?;
+            Some(help.signatures[0].label.clone())
+        };
+
+        assert_eq!(label("v = new Vector(?"), Some("new Vector(x, y)".into()));
+        // calling a constructor without `new` still shows what it takes
+        assert_eq!(label("v = Vector(1, ?"), Some("new Vector(x, y)".into()));
+        // `new` can't call a builtin, so it's the user's function
+        assert_eq!(label("v = new point(?"), Some("point(x)".into()));
+        assert_eq!(label("v = point(?"), Some("point(): Real".into()));
+        assert_eq!(label("v = new Nothing(?"), None);
+    }
+
+    #[test]
+    fn builtin_parameters() {
+        let param = |name: &str, gm_type: &str, optional: bool| gm_doc::Parameter {
+            name: name.to_string(),
+            description: String::new(),
+            gm_type: gm_type.to_string(),
+            optional,
+        };
+        let func = gm_doc::Function {
+            name: "array_push".to_string(),
+            parameters: vec![
+                param("array", "Array", false),
+                param("value", "Any", false),
+                param("...", "Any", true),
+            ],
+            description: String::new(),
+            returns: "Undefined".to_string(),
+            deprecated: false,
+            pure: false,
+            link: None,
+        };
+
+        let signature = builtin_signature(&func);
+        assert_eq!(
+            signature.label,
+            "array_push(array: Array, value: Any, [...: Any]): Undefined"
+        );
+        assert_eq!(clamp_variadic(&signature, 1), 1);
+        assert_eq!(clamp_variadic(&signature, 5), 2);
+
+        let mut func = func;
+        func.parameters.pop();
+        let signature = builtin_signature(&func);
+        assert_eq!(clamp_variadic(&signature, 5), 5);
     }
 }
@@ -1,6 +1,13 @@
 use itertools::Itertools;
 use lsp_types::MarkedString;
 use yy_boss::YypBoss;
+use yy_typings::YypConfig;
+
+use crate::gml::{Local, LocalKind, SyntaxTree};
+use crate::services::{
+    EnumMemberSymbol, InstanceVariable, ResourceLookup, ResourceLookupData, Symbol, SymbolIndex,
+    SymbolKind, VariableOrigin,
+};
 
 use strum::IntoEnumIterator;
 
@@ -21,6 +28,10 @@ pub enum StdCompletionKind {
     Function,
     Variable,
     Constant,
+    UserFunction,
+    Enum,
+    GlobalVariable,
+    Macro,
     Object,
 }
 
@@ -34,7 +45,8 @@ pub fn detailed_docs_data(
     input: &str,
     attempt: &[StdCompletionKind],
     gm_manual: &gm_doc::Program,
-    _yyp_boss: &YypBoss,
+    yyp_boss: &YypBoss,
+    symbols: &SymbolIndex,
 ) -> Option<DetailedDocsData> {
     for kind in StdCompletionKind::iter() {
         if attempt.contains(&kind) {
@@ -50,8 +62,8 @@ pub fn detailed_docs_data(
                         );
 
                         // gather documentation:
-                        let mut description =
-                            vec![MarkedString::from_markdown(func.description.to_string())];
+                        let mut description = deprecated_banner(func.deprecated);
+                        description.push(MarkedString::from_markdown(func.description.to_string()));
                         if let Some(link) = &func.link {
                             description.push(MarkedString::from_markdown(format!(
                                 "Go to [{}]({})",
@@ -69,9 +81,10 @@ pub fn detailed_docs_data(
                     if let Some(variable) = gm_manual.variables.get(input) {
                         let detail = format!("{}: {}", variable.name, variable.returns);
 
-                        let mut description = vec![MarkedString::from_markdown(
+                        let mut description = deprecated_banner(variable.deprecated);
+                        description.push(MarkedString::from_markdown(
                             variable.description.to_string(),
-                        )];
+                        ));
                         if let Some(link) = &variable.link {
                             description.push(MarkedString::from_markdown(format!(
                                 "Go to [{}]({})",
@@ -90,9 +103,10 @@ pub fn detailed_docs_data(
                         {
                             let detail = constant.name.clone();
 
-                            let mut description = vec![MarkedString::from_markdown(
+                            let mut description = deprecated_banner(constant.deprecated);
+                            description.push(MarkedString::from_markdown(
                                 constant.description.to_string(),
-                            )];
+                            ));
                             if let Some(link) = &constant.link {
                                 description.push(MarkedString::from_markdown(format!(
                                     "Go to [{}]({})",
@@ -114,9 +128,349 @@ pub fn detailed_docs_data(
                         description: vec![],
                     })
                 }
+                StdCompletionKind::UserFunction => {
+                    let symbol = symbols
+                        .get(input)
+                        .iter()
+                        .find(|v| matches!(v.kind, SymbolKind::Function(_)));
+                    if let Some(symbol) = symbol {
+                        return Some(symbol_docs_data(symbol));
+                    }
+                }
+                StdCompletionKind::Enum => {
+                    let symbol = symbols
+                        .get(input)
+                        .iter()
+                        .find(|v| matches!(v.kind, SymbolKind::Enum(_)));
+                    if let Some(symbol) = symbol {
+                        return Some(symbol_docs_data(symbol));
+                    }
+                }
+                StdCompletionKind::GlobalVariable => {
+                    let symbol = symbols
+                        .get(input)
+                        .iter()
+                        .find(|v| v.kind == SymbolKind::GlobalVariable);
+                    if let Some(symbol) = symbol {
+                        return Some(symbol_docs_data(symbol));
+                    }
+                }
+                StdCompletionKind::Macro => {
+                    if let Some(output) = macro_docs_data(input, symbols, &yyp_boss.yyp().configs) {
+                        return Some(output);
+                    }
+                }
             }
         }
     }
 
     None
 }
+
+/// Starts the docs of deprecated builtins with a warning.
+fn deprecated_banner(deprecated: bool) -> Vec<MarkedString> {
+    if deprecated {
+        vec![MarkedString::from_markdown(
+            "**Deprecated:** this is only kept for compatibility, and may be removed in a \
+             future version of GameMaker."
+                .to_string(),
+        )]
+    } else {
+        vec![]
+    }
+}
+
+fn symbol_docs_data(symbol: &Symbol) -> DetailedDocsData {
+    let mut detail = symbol.detail();
+    let mut description = vec![];
+
+    if let SymbolKind::Function(func) = &symbol.kind {
+        let docs = &func.docs;
+        if let Some(gm_type) = docs.returns.as_ref().and_then(|v| v.gm_type.as_ref()) {
+            detail = format!("{}: {}", detail, gm_type);
+        }
+
+        if docs.description.is_empty() == false {
+            description.push(MarkedString::from_markdown(docs.description.clone()));
+        }
+        if docs.params.is_empty() == false {
+            let params: Vec<_> = docs
+                .params
+                .iter()
+                .map(|param| {
+                    let gm_type = param
+                        .gm_type
+                        .as_ref()
+                        .map(|v| format!(" *{}*", v))
+                        .unwrap_or_default();
+                    format!("- `{}`{}: {}", param.name, gm_type, param.description)
+                })
+                .collect();
+            description.push(MarkedString::from_markdown(params.join("\n")));
+        }
+        if let Some(returns) = &docs.returns {
+            if returns.description.is_empty() == false {
+                description.push(MarkedString::from_markdown(format!(
+                    "Returns {}",
+                    returns.description
+                )));
+            }
+        }
+    }
+
+    description.push(MarkedString::from_markdown(format!(
+        "Declared in `{}` on line {}",
+        symbol.source,
+        symbol.range.start.line + 1
+    )));
+
+    DetailedDocsData {
+        detail,
+        description,
+    }
+}
+
+/// Describes a local by the line it's declared on.
+pub fn local_docs_data(txt: &str, tree: &SyntaxTree, local: &Local<'_>) -> DetailedDocsData {
+    let line_start = txt[..local.ident.span.start]
+        .rfind('\n')
+        .map(|v| v + 1)
+        .unwrap_or_default();
+    let line_end = txt[local.ident.span.start..]
+        .find('\n')
+        .map(|v| v + local.ident.span.start)
+        .unwrap_or(txt.len());
+
+    let kind = match local.kind {
+        LocalKind::Var => "Local variable",
+        LocalKind::Param => "Parameter",
+        LocalKind::Static => "Static variable",
+        LocalKind::Catch => "Caught exception",
+    };
+    let line = tree.range(txt, local.ident.span).start.line + 1;
+
+    DetailedDocsData {
+        detail: txt[line_start..line_end].trim().to_string(),
+        description: vec![MarkedString::from_markdown(format!(
+            "{} declared on line {}",
+            kind, line
+        ))],
+    }
+}
+
+pub fn instance_variable_docs_data(variable: &InstanceVariable) -> DetailedDocsData {
+    let origin = match &variable.origin {
+        VariableOrigin::Event { event, range, .. } => {
+            let source = ResourceLookup {
+                name: variable.object.clone(),
+                data: ResourceLookupData::Object(*event),
+            };
+            format!("Declared in `{}` on line {}", source, range.start.line + 1)
+        }
+        VariableOrigin::Property { value } => format!(
+            "Object Variable of `{}`, defaulting to `{}`",
+            variable.object, value
+        ),
+    };
+
+    DetailedDocsData {
+        detail: format!("{}.{}", variable.object, variable.name),
+        description: vec![MarkedString::from_markdown(origin)],
+    }
+}
+
+pub fn enum_member_detail(enum_name: &str, member: &EnumMemberSymbol) -> String {
+    match member.value {
+        Some(value) => format!("{}.{} = {}", enum_name, member.name, value),
+        None => format!("{}.{}", enum_name, member.name),
+    }
+}
+
+/// Splits a manual type into the types it's a union of. The manual mostly separates them with
+/// commas, like `Id.Instance,Asset.GMObject`, but sometimes with `|`. A container keeps just its
+/// outer type, so `Array[Real]` and `Array<Real>` are both `Array`, and commas inside of one
+/// don't split anything. A bare `[Real]` is left as it is, since we don't know what it means.
+pub fn gm_type_union(gm_type: &str) -> Vec<&str> {
+    let mut output = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in gm_type.char_indices() {
+        match c {
+            '[' | '<' => depth += 1,
+            ']' | '>' => depth = depth.saturating_sub(1),
+            ',' | '|' if depth == 0 => {
+                output.push(&gm_type[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    output.push(&gm_type[start..]);
+
+    output
+        .into_iter()
+        .map(|v| {
+            let v = v.trim();
+            match v.find(['[', '<']) {
+                Some(i) if i > 0 => v[..i].trim_end(),
+                _ => v,
+            }
+        })
+        .filter(|v| v.is_empty() == false)
+        .collect()
+}
+
+/// Describes every definition of a macro, grouped by the build configuration it belongs to.
+/// Unconfigured macros come first, then configurations in the order the `.yyp` lists them.
+fn macro_docs_data(
+    input: &str,
+    symbols: &SymbolIndex,
+    configs: &YypConfig,
+) -> Option<DetailedDocsData> {
+    let mut macros: Vec<_> = symbols
+        .get(input)
+        .iter()
+        .filter_map(|symbol| match &symbol.kind {
+            SymbolKind::Macro(mac) => Some((symbol, mac)),
+            _ => None,
+        })
+        .collect();
+    if macros.is_empty() {
+        return None;
+    }
+
+    let config_order: Vec<_> = config_names(configs).collect();
+    macros.sort_by_key(|(_, mac)| {
+        mac.config.as_ref().map(|config| {
+            config_order
+                .iter()
+                .position(|v| *v == config)
+                .unwrap_or(config_order.len())
+        })
+    });
+
+    let mut description = vec![];
+    for (symbol, mac) in macros {
+        let configuration = match &mac.config {
+            None => "All configurations".to_string(),
+            Some(config) => match config_path(configs, config) {
+                Some(path) => format!("Configuration `{}`", path.join(" / ")),
+                None => format!("Configuration `{}` (not found in the project)", config),
+            },
+        };
+        description.push(MarkedString::from_markdown(configuration));
+
+        description.push(MarkedString::from_language_code(
+            "gml-gms2".to_string(),
+            mac.expansion(),
+        ));
+        description.push(MarkedString::from_markdown(format!(
+            "Declared in `{}` on line {}",
+            symbol.source,
+            symbol.range.start.line + 1
+        )));
+    }
+
+    Some(DetailedDocsData {
+        detail: format!("#macro {}", input),
+        description,
+    })
+}
+
+/// The names of every configuration from the root down to `name`.
+fn config_path<'a>(config: &'a YypConfig, name: &str) -> Option<Vec<&'a str>> {
+    if config.name == name {
+        return Some(vec![&config.name]);
+    }
+
+    config.children.iter().find_map(|child| {
+        let mut path = config_path(child, name)?;
+        path.insert(0, &config.name);
+        Some(path)
+    })
+}
+
+/// Every configuration name, parents before their children.
+fn config_names(config: &YypConfig) -> Box<dyn Iterator<Item = &String> + '_> {
+    Box::new(std::iter::once(&config.name).chain(config.children.iter().flat_map(config_names)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::gml::SyntaxTree;
+
+    #[test]
+    fn macro_configurations() {
+        let txt = "#macro SPEED 4
+#macro Debug:SPEED 8
+#macro Lost:SPEED 16";
+        let mut symbols = SymbolIndex::default();
+        symbols.update_file(
+            Path::new("/project/scripts/scr_macros/scr_macros.gml"),
+            &ResourceLookup {
+                name: "scr_macros".to_string(),
+                data: ResourceLookupData::Script,
+            },
+            txt,
+            &SyntaxTree::parse(txt),
+        );
+        let configs = YypConfig {
+            name: "Default".to_string(),
+            children: vec![YypConfig {
+                name: "Debug".to_string(),
+                children: vec![],
+            }],
+        };
+
+        let docs = macro_docs_data("SPEED", &symbols, &configs).unwrap();
+        assert_eq!(docs.detail, "#macro SPEED");
+
+        let markdown: Vec<String> = docs
+            .description
+            .into_iter()
+            .map(|v| match v {
+                MarkedString::String(v) => v,
+                MarkedString::LanguageString(v) => v.value,
+            })
+            .collect();
+        assert_eq!(
+            markdown,
+            vec![
+                "All configurations",
+                "4",
+                "Declared in `scr_macros` on line 1",
+                "Configuration `Default / Debug`",
+                "8",
+                "Declared in `scr_macros` on line 2",
+                "Configuration `Lost` (not found in the project)",
+                "16",
+                "Declared in `scr_macros` on line 3",
+            ]
+        );
+
+        assert!(macro_docs_data("SLOW", &symbols, &configs).is_none());
+    }
+
+    #[test]
+    fn gm_type_unions() {
+        assert_eq!(
+            gm_type_union("Id.TileMapElement,Asset.GMObject,Id.Instance,Constant.All,Array"),
+            vec![
+                "Id.TileMapElement",
+                "Asset.GMObject",
+                "Id.Instance",
+                "Constant.All",
+                "Array"
+            ]
+        );
+        assert_eq!(gm_type_union("Real | String"), vec!["Real", "String"]);
+        assert_eq!(
+            gm_type_union("Array[Real,String], Array<Id.Instance>,[Real]"),
+            vec!["Array", "Array", "[Real]"]
+        );
+        assert_eq!(gm_type_union(""), Vec::<&str>::new());
+    }
+}
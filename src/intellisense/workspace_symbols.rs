@@ -0,0 +1,34 @@
+use lsp_types::SymbolInformation;
+
+use super::definition;
+use crate::services::{SymbolIndex, SymbolKind};
+
+/// Every project symbol whose name contains the query, ignoring case.
+pub fn workspace_symbols(query: &str, symbols: &SymbolIndex) -> Vec<SymbolInformation> {
+    let query = query.to_lowercase();
+
+    symbols
+        .iter()
+        .filter(|(name, _)| name.to_lowercase().contains(&query))
+        .flat_map(|(_, declarations)| declarations)
+        .filter_map(|symbol| {
+            let kind = match &symbol.kind {
+                SymbolKind::Function(func) if func.constructor => lsp_types::SymbolKind::CLASS,
+                SymbolKind::Function(_) => lsp_types::SymbolKind::FUNCTION,
+                SymbolKind::Macro(_) => lsp_types::SymbolKind::CONSTANT,
+                SymbolKind::Enum(_) => lsp_types::SymbolKind::ENUM,
+                SymbolKind::GlobalVariable => lsp_types::SymbolKind::VARIABLE,
+            };
+
+            #[allow(deprecated)]
+            Some(SymbolInformation {
+                name: symbol.name.clone(),
+                kind,
+                tags: None,
+                deprecated: None,
+                location: definition::location(symbol)?,
+                container_name: Some(symbol.source.to_string()),
+            })
+        })
+        .collect()
+}
@@ -85,3 +85,21 @@ impl From<lsp_types::Range> for Range {
         }
     }
 }
+
+impl From<Position> for lsp_types::Position {
+    fn from(o: Position) -> Self {
+        Self {
+            line: o.line as u32,
+            character: o.column as u32,
+        }
+    }
+}
+
+impl From<Range> for lsp_types::Range {
+    fn from(o: Range) -> Self {
+        Self {
+            start: o.start.into(),
+            end: o.end.into(),
+        }
+    }
+}
@@ -12,4 +12,8 @@ impl lsp_types::request::Request for YyBossRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InitializationOptions {
     pub working_directory: String,
+    /// Names which are made at runtime, like with `variable_instance_set`, and so shouldn't be
+    /// reported as undefined. A trailing `*` allows every name with that prefix.
+    #[serde(default)]
+    pub allowed_identifiers: Vec<String>,
 }
@@ -1,17 +1,36 @@
 #![allow(clippy::bool_comparison)]
 
+use std::collections::{HashMap, HashSet};
+
 use anyhow::Result as AnyResult;
 use log::info;
 use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId, Response};
 use lsp_types::{
-    notification::{DidChangeTextDocument, DidOpenTextDocument, DidSaveTextDocument},
-    request::{Completion, HoverRequest, ResolveCompletionItem, SignatureHelpRequest},
-    CompletionList, Hover, InitializeParams, ServerCapabilities, SignatureHelp,
-    SignatureHelpOptions, WorkDoneProgressOptions,
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{
+        CodeActionRequest, Completion, DocumentDiagnosticRequest, GotoDefinition, HoverRequest,
+        PrepareRenameRequest, References, Rename, ResolveCompletionItem, SignatureHelpRequest,
+        WorkspaceDiagnosticRequest, WorkspaceSymbolRequest,
+    },
+    CompletionList, DiagnosticOptions, DiagnosticServerCapabilities, DocumentDiagnosticReport,
+    DocumentDiagnosticReportKind, FullDocumentDiagnosticReport, GotoDefinitionResponse, Hover,
+    HoverContents, InitializeParams, Location, OneOf, PrepareRenameResponse,
+    PublishDiagnosticsParams, RelatedFullDocumentDiagnosticReport,
+    RelatedUnchangedDocumentDiagnosticReport, ResourceOp, ServerCapabilities, SignatureHelp,
+    SignatureHelpOptions, Url, WorkDoneProgressOptions, WorkspaceDiagnosticReport,
+    WorkspaceDiagnosticReportResult, WorkspaceDocumentDiagnosticReport,
+    WorkspaceFullDocumentDiagnosticReport, WorkspaceSymbolResponse,
+    WorkspaceUnchangedDocumentDiagnosticReport,
 };
 
+mod gml;
+use gml::SyntaxTree;
+
 mod intellisense;
-use intellisense::*;
+use intellisense::{instance::InstanceContext, project::Project, *};
 
 mod services;
 use services::{Boss, ServicesProvider};
@@ -32,6 +51,7 @@ fn main() -> AnyResult<()> {
     let server_capabs = ServerCapabilities {
         text_document_sync: Some(
             lsp_types::TextDocumentSyncOptions {
+                open_close: Some(true),
                 change: Some(lsp_types::TextDocumentSyncKind::INCREMENTAL),
                 save: Some(
                     lsp_types::SaveOptions {
@@ -55,6 +75,20 @@ fn main() -> AnyResult<()> {
                 work_done_progress: None,
             },
         }),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Right(lsp_types::RenameOptions {
+            prepare_provider: Some(true),
+            work_done_progress_options: WorkDoneProgressOptions::default(),
+        })),
+        workspace_symbol_provider: Some(OneOf::Left(true)),
+        code_action_provider: Some(lsp_types::CodeActionProviderCapability::Simple(true)),
+        diagnostic_provider: Some(DiagnosticServerCapabilities::Options(DiagnosticOptions {
+            identifier: Some("gm-code".to_string()),
+            inter_file_dependencies: true,
+            workspace_diagnostics: true,
+            work_done_progress_options: WorkDoneProgressOptions::default(),
+        })),
 
         ..ServerCapabilities::default()
     };
@@ -74,6 +108,16 @@ fn main() -> AnyResult<()> {
 fn main_loop(connection: &Connection, params: InitializeParams) -> AnyResult<()> {
     info!("starting main loop");
     let services = ServicesProvider::new();
+    // clients which pull diagnostics don't want them pushed as well
+    let push_diagnostics = params
+        .capabilities
+        .text_document
+        .as_ref()
+        .is_some_and(|v| v.diagnostic.is_some())
+        == false;
+    let edit_capabilities = rename::EditCapabilities::new(&params.capabilities);
+    // the client keeps these up to date for us with `didChange`, but not the rest
+    let mut open_documents: HashSet<Url> = HashSet::new();
     let final_path =
         params
             .workspace_folders
@@ -106,6 +150,7 @@ fn main_loop(connection: &Connection, params: InitializeParams) -> AnyResult<()>
     let mut boss = Boss::new(final_path);
     let initialization_options: lsp::InitializationOptions =
         serde_json::from_value(params.initialization_options.unwrap()).unwrap();
+    let rules = diagnostics::RuleRegistry::new(initialization_options.allowed_identifiers.clone());
 
     let working_directory = camino::Utf8PathBuf::from(&initialization_options.working_directory);
 
@@ -120,17 +165,13 @@ fn main_loop(connection: &Connection, params: InitializeParams) -> AnyResult<()>
                     Ok((id, params)) => {
                         info!("received completion requestion msg {}: {:?}", id, params);
                         let position = params.text_document_position.position;
+                        let uri = &params.text_document_position.text_document.uri;
 
                         let result: CompletionList = boss
-                            .get_text_document(&params.text_document_position.text_document.uri)
-                            .and_then(|v| {
-                                Boss::get_word_in_document(v, position).map(|word| {
-                                    completion::initial_completion(
-                                        word,
-                                        services.gm_manual(),
-                                        &boss.yy_boss,
-                                    )
-                                })
+                            .get_document(uri)
+                            .and_then(|(txt, tree)| {
+                                let offset = tree.offset(txt, position.into())?;
+                                completion_at(&boss, &services, uri, txt, tree, offset)
                             })
                             .unwrap_or_default();
 
@@ -152,6 +193,7 @@ fn main_loop(connection: &Connection, params: InitializeParams) -> AnyResult<()>
                             completion_item,
                             services.gm_manual(),
                             &boss.yy_boss,
+                            &boss.symbols,
                         );
 
                         let result = serde_json::to_value(completion_item).unwrap();
@@ -170,19 +212,12 @@ fn main_loop(connection: &Connection, params: InitializeParams) -> AnyResult<()>
                 let request = match cast::<HoverRequest>(request) {
                     Ok((id, params)) => {
                         let position = params.text_document_position_params;
+                        let uri = &position.text_document.uri;
 
-                        let result: Option<Hover> = boss
-                            .get_text_document(&position.text_document.uri)
-                            .and_then(|v| {
-                                Boss::get_word_in_document_full(v, position.position).and_then(
-                                    |word| {
-                                        hover::hover_on_word(
-                                            word,
-                                            services.gm_manual(),
-                                            &boss.yy_boss,
-                                        )
-                                    },
-                                )
+                        let result: Option<Hover> =
+                            boss.get_document(uri).and_then(|(txt, tree)| {
+                                let offset = tree.offset(txt, position.position.into())?;
+                                hover_at(&boss, &services, uri, txt, tree, offset)
                             });
 
                         let resp = Response {
@@ -204,14 +239,46 @@ fn main_loop(connection: &Connection, params: InitializeParams) -> AnyResult<()>
                 let request = match cast::<SignatureHelpRequest>(request) {
                     Ok((id, params)) => {
                         let result: Option<SignatureHelp> = boss
-                            .get_text_document(
-                                &params.text_document_position_params.text_document.uri,
-                            )
-                            .and_then(|txt| {
+                            .get_document(&params.text_document_position_params.text_document.uri)
+                            .and_then(|(txt, tree)| {
                                 signature::signature_help(
                                     txt,
+                                    tree,
                                     params.text_document_position_params.position.into(),
                                     services.gm_manual(),
+                                    &boss.symbols,
+                                )
+                            });
+
+                        let resp = Response {
+                            id,
+                            result: Some(
+                                result
+                                    .map(|v| serde_json::to_value(v).unwrap())
+                                    .unwrap_or(serde_json::Value::Null),
+                            ),
+                            error: None,
+                        };
+                        connection.sender.send(Message::Response(resp))?;
+
+                        continue;
+                    }
+                    Err(req) => req,
+                };
+
+                let request = match cast::<GotoDefinition>(request) {
+                    Ok((id, params)) => {
+                        let position = params.text_document_position_params;
+
+                        let result: Option<GotoDefinitionResponse> = boss
+                            .get_document(&position.text_document.uri)
+                            .and_then(|(txt, tree)| {
+                                let offset = tree.offset(txt, position.position.into())?;
+                                definition::definition_at(
+                                    &project(&boss, &services),
+                                    &position.text_document.uri,
+                                    boss.get_event(&position.text_document.uri),
+                                    offset,
                                 )
                             });
 
@@ -231,6 +298,287 @@ fn main_loop(connection: &Connection, params: InitializeParams) -> AnyResult<()>
                     Err(req) => req,
                 };
 
+                let request = match cast::<WorkspaceSymbolRequest>(request) {
+                    Ok((id, params)) => {
+                        let result = WorkspaceSymbolResponse::Flat(
+                            workspace_symbols::workspace_symbols(&params.query, &boss.symbols),
+                        );
+
+                        let resp = Response {
+                            id,
+                            result: Some(serde_json::to_value(result).unwrap()),
+                            error: None,
+                        };
+                        connection.sender.send(Message::Response(resp))?;
+
+                        continue;
+                    }
+                    Err(req) => req,
+                };
+
+                let request = match cast::<References>(request) {
+                    Ok((id, params)) => {
+                        let position = params.text_document_position;
+
+                        let result: Option<Vec<Location>> = boss
+                            .get_document(&position.text_document.uri)
+                            .and_then(|(txt, tree)| {
+                                let offset = tree.offset(txt, position.position.into())?;
+                                references::references_at(
+                                    &project(&boss, &services),
+                                    &position.text_document.uri,
+                                    offset,
+                                    params.context.include_declaration,
+                                )
+                            });
+
+                        let resp = Response {
+                            id,
+                            result: Some(serde_json::to_value(result).unwrap()),
+                            error: None,
+                        };
+                        connection.sender.send(Message::Response(resp))?;
+
+                        continue;
+                    }
+                    Err(req) => req,
+                };
+
+                let request = match cast::<PrepareRenameRequest>(request) {
+                    Ok((id, params)) => {
+                        let result: Option<PrepareRenameResponse> = boss
+                            .get_document(&params.text_document.uri)
+                            .and_then(|(txt, tree)| {
+                                let offset = tree.offset(txt, params.position.into())?;
+                                rename::prepare_rename_at(
+                                    &project(&boss, &services),
+                                    &params.text_document.uri,
+                                    offset,
+                                )
+                            });
+
+                        let resp = Response {
+                            id,
+                            result: Some(serde_json::to_value(result).unwrap()),
+                            error: None,
+                        };
+                        connection.sender.send(Message::Response(resp))?;
+
+                        continue;
+                    }
+                    Err(req) => req,
+                };
+
+                let request = match cast::<Rename>(request) {
+                    Ok((id, params)) => {
+                        let position = params.text_document_position;
+                        if rename::is_identifier(&params.new_name) == false {
+                            let resp = Response::new_err(
+                                id,
+                                lsp_server::ErrorCode::InvalidParams as i32,
+                                format!("`{}` isn't a valid name", params.new_name),
+                            );
+                            connection.sender.send(Message::Response(resp))?;
+
+                            continue;
+                        }
+
+                        let result = boss.get_document(&position.text_document.uri).and_then(
+                            |(txt, tree)| {
+                                let offset = tree.offset(txt, position.position.into())?;
+                                rename::rename_at(
+                                    &project(&boss, &services),
+                                    &position.text_document.uri,
+                                    offset,
+                                    &params.new_name,
+                                )
+                            },
+                        );
+
+                        // moving an asset's files needs the client to rename them
+                        if let Some(asset) = result.as_ref().and_then(|v| v.asset.as_ref()) {
+                            if edit_capabilities.rename_files == false {
+                                let resp = Response::new_err(
+                                    id,
+                                    lsp_server::ErrorCode::RequestFailed as i32,
+                                    format!(
+                                        "`{}` is an asset, and this editor can't rename its files",
+                                        asset
+                                    ),
+                                );
+                                connection.sender.send(Message::Response(resp))?;
+
+                                continue;
+                            }
+                        }
+
+                        let Some(result) = result else {
+                            let resp = Response {
+                                id,
+                                result: Some(serde_json::Value::Null),
+                                error: None,
+                            };
+                            connection.sender.send(Message::Response(resp))?;
+
+                            continue;
+                        };
+
+                        let resp = Response {
+                            id,
+                            result: Some(
+                                serde_json::to_value(
+                                    result.workspace_edit(edit_capabilities.document_changes),
+                                )
+                                .unwrap(),
+                            ),
+                            error: None,
+                        };
+                        connection.sender.send(Message::Response(resp))?;
+
+                        let (renamed, moved) = apply_rename(boss, &result, &open_documents);
+                        boss = renamed;
+                        if push_diagnostics {
+                            // the client would otherwise keep showing what we last said about
+                            // the documents which are now somewhere else
+                            for uri in &moved {
+                                send_diagnostics(connection, uri.clone(), vec![])?;
+                            }
+                            let touched: Vec<Url> = result
+                                .edits
+                                .keys()
+                                .chain(&moved)
+                                .map(|v| result.moved(v))
+                                .collect();
+                            publish_documents(connection, &mut boss, &services, &rules, &touched)?;
+                        }
+
+                        continue;
+                    }
+                    Err(req) => req,
+                };
+
+                let request = match cast::<CodeActionRequest>(request) {
+                    Ok((id, params)) => {
+                        let result = code_actions::code_actions(
+                            &params.text_document.uri,
+                            &params.context.diagnostics,
+                        );
+
+                        let resp = Response {
+                            id,
+                            result: Some(serde_json::to_value(result).unwrap()),
+                            error: None,
+                        };
+                        connection.sender.send(Message::Response(resp))?;
+
+                        continue;
+                    }
+                    Err(req) => req,
+                };
+
+                let request = match cast::<DocumentDiagnosticRequest>(request) {
+                    Ok((id, params)) => {
+                        let report = pull_diagnostics(
+                            &mut boss,
+                            &services,
+                            &rules,
+                            &params.text_document.uri,
+                            params.previous_result_id.as_deref(),
+                        )
+                        .unwrap_or_else(|| FullDocumentDiagnosticReport::default().into());
+
+                        let result = match report {
+                            DocumentDiagnosticReportKind::Full(report) => {
+                                DocumentDiagnosticReport::Full(
+                                    RelatedFullDocumentDiagnosticReport {
+                                        related_documents: None,
+                                        full_document_diagnostic_report: report,
+                                    },
+                                )
+                            }
+                            DocumentDiagnosticReportKind::Unchanged(report) => {
+                                DocumentDiagnosticReport::Unchanged(
+                                    RelatedUnchangedDocumentDiagnosticReport {
+                                        related_documents: None,
+                                        unchanged_document_diagnostic_report: report,
+                                    },
+                                )
+                            }
+                        };
+
+                        let resp = Response {
+                            id,
+                            result: Some(serde_json::to_value(result).unwrap()),
+                            error: None,
+                        };
+                        connection.sender.send(Message::Response(resp))?;
+
+                        continue;
+                    }
+                    Err(req) => req,
+                };
+
+                let request = match cast::<WorkspaceDiagnosticRequest>(request) {
+                    Ok((id, params)) => {
+                        // a change to one file can change the diagnostics of any other, so
+                        // everything is rechecked, but only what's changed is sent back
+                        let previous_result_ids: HashMap<&Url, &str> = params
+                            .previous_result_ids
+                            .iter()
+                            .map(|v| (&v.uri, v.value.as_str()))
+                            .collect();
+
+                        let mut items = vec![];
+                        for uri in boss.gml_documents() {
+                            let previous_result_id = previous_result_ids.get(&uri).copied();
+                            let Some(report) = pull_diagnostics(
+                                &mut boss,
+                                &services,
+                                &rules,
+                                &uri,
+                                previous_result_id,
+                            ) else {
+                                continue;
+                            };
+
+                            items.push(match report {
+                                DocumentDiagnosticReportKind::Full(report) => {
+                                    WorkspaceDocumentDiagnosticReport::Full(
+                                        WorkspaceFullDocumentDiagnosticReport {
+                                            uri,
+                                            version: None,
+                                            full_document_diagnostic_report: report,
+                                        },
+                                    )
+                                }
+                                DocumentDiagnosticReportKind::Unchanged(report) => {
+                                    WorkspaceDocumentDiagnosticReport::Unchanged(
+                                        WorkspaceUnchangedDocumentDiagnosticReport {
+                                            uri,
+                                            version: None,
+                                            unchanged_document_diagnostic_report: report,
+                                        },
+                                    )
+                                }
+                            });
+                        }
+                        let result =
+                            WorkspaceDiagnosticReportResult::Report(WorkspaceDiagnosticReport {
+                                items,
+                            });
+
+                        let resp = Response {
+                            id,
+                            result: Some(serde_json::to_value(result).unwrap()),
+                            error: None,
+                        };
+                        connection.sender.send(Message::Response(resp))?;
+
+                        continue;
+                    }
+                    Err(req) => req,
+                };
+
                 match cast::<lsp::YyBossRequest>(request) {
                     Ok((id, param)) => {
                         let output = yy_boss::cli::parse_command(
@@ -255,9 +603,20 @@ fn main_loop(connection: &Connection, params: InitializeParams) -> AnyResult<()>
             Message::Notification(not) => {
                 let not = match cast_notification::<DidOpenTextDocument>(not) {
                     Ok(v) => {
+                        open_documents.insert(v.text_document.uri.clone());
                         if let Some(txt) = boss.get_text_document_mut(&v.text_document.uri) {
                             *txt = v.text_document.text;
                         }
+                        boss.reparse(&v.text_document.uri);
+                        if push_diagnostics {
+                            publish_diagnostics(
+                                connection,
+                                &mut boss,
+                                &services,
+                                &rules,
+                                &v.text_document.uri,
+                            )?;
+                        }
                         continue;
                     }
                     Err(req) => req,
@@ -265,20 +624,20 @@ fn main_loop(connection: &Connection, params: InitializeParams) -> AnyResult<()>
 
                 let not = match cast_notification::<DidChangeTextDocument>(not) {
                     Ok(v) => {
-                        if let Some(txt) = boss.get_text_document_mut(&v.text_document.uri) {
-                            for change in v.content_changes {
-                                if let Some(range) = change.range {
-                                    let range: lsp::Range = range.into();
-                                    let start = range.start.get_idx(txt).unwrap();
-                                    let end = range.end.get_idx(txt).unwrap();
-
-                                    txt.replace_range(start..end, &change.text);
-                                } else {
-                                    *txt = change.text;
-                                }
+                        for change in v.content_changes {
+                            if boss.apply_change(&v.text_document.uri, change) == false {
+                                log::warn!("text_document.uri not found {}", v.text_document.uri);
+                                break;
                             }
-                        } else {
-                            log::warn!("text_document.uri not found {}", v.text_document.uri);
+                        }
+                        if push_diagnostics {
+                            publish_diagnostics(
+                                connection,
+                                &mut boss,
+                                &services,
+                                &rules,
+                                &v.text_document.uri,
+                            )?;
                         }
 
                         continue;
@@ -286,18 +645,290 @@ fn main_loop(connection: &Connection, params: InitializeParams) -> AnyResult<()>
                     Err(req) => req,
                 };
 
-                let _not = match cast_notification::<DidSaveTextDocument>(not) {
+                let not = match cast_notification::<DidSaveTextDocument>(not) {
                     Ok(v) => {
                         if let Some(txt) = boss.get_text_document_mut(&v.text_document.uri) {
                             *txt = v.text.unwrap();
                         }
+                        boss.reparse(&v.text_document.uri);
+                        if push_diagnostics {
+                            publish_diagnostics(
+                                connection,
+                                &mut boss,
+                                &services,
+                                &rules,
+                                &v.text_document.uri,
+                            )?;
+                        }
                         continue;
                     }
                     Err(e) => e,
                 };
+
+                let _not = match cast_notification::<DidCloseTextDocument>(not) {
+                    Ok(v) => {
+                        open_documents.remove(&v.text_document.uri);
+                        if boss.clear_diagnostics(&v.text_document.uri) && push_diagnostics {
+                            send_diagnostics(connection, v.text_document.uri, vec![])?;
+                        }
+                        continue;
+                    }
+                    Err(e) => e,
+                };
+            }
+        }
+    }
+    Ok(())
+}
+
+fn completion_at(
+    boss: &Boss,
+    services: &ServicesProvider,
+    uri: &Url,
+    txt: &str,
+    tree: &SyntaxTree,
+    offset: usize,
+) -> Option<CompletionList> {
+    let word = tree.word_before(txt, offset)?;
+    let context = InstanceContext::at(tree, offset, boss.get_event(uri), &boss.objects);
+
+    // after a `.`, only the members of what's before it make sense
+    if let Some(receiver) = tree.receiver_before(txt, offset) {
+        if let Some(members) = completion::member_completion(receiver, word, &boss.symbols) {
+            return Some(members);
+        }
+        if let Some(object) = context.resolve(receiver, &boss.objects) {
+            return Some(completion::instance_member_completion(
+                word,
+                &boss.objects.variables(&object),
+                services.gm_manual(),
+            ));
+        }
+    }
+
+    let mut output = completion::initial_completion(
+        word,
+        services.gm_manual(),
+        &boss.yy_boss,
+        &boss.symbols,
+        &context.variables(&boss.objects),
+        &completion::constant_classes(tree, offset, services.gm_manual()),
+    );
+    output
+        .items
+        .splice(0..0, completion::local_completion(word, txt, tree, offset));
+
+    Some(output)
+}
+
+fn hover_at(
+    boss: &Boss,
+    services: &ServicesProvider,
+    uri: &Url,
+    txt: &str,
+    tree: &SyntaxTree,
+    offset: usize,
+) -> Option<Hover> {
+    let (word, span) = tree.word_at(txt, offset)?;
+    let receiver = tree.receiver_before(txt, span.start);
+    let context = InstanceContext::at(tree, span.start, boss.get_event(uri), &boss.objects);
+
+    let mut hover = receiver
+        .and_then(|receiver| hover::hover_on_member(receiver, word, &boss.symbols))
+        .or_else(|| {
+            let local = tree.local(word, span.end).filter(|_| receiver.is_none())?;
+            Some(hover::hover_on_local(txt, tree, &local))
+        })
+        .or_else(|| {
+            let object = match receiver {
+                Some(receiver) => context.resolve(receiver, &boss.objects)?,
+                None => context.self_object.clone()?,
+            };
+            let variable = boss.objects.variable(&object, word)?;
+            let is_builtin = services.gm_manual().variables.contains_key(word);
+
+            (is_builtin == false).then(|| hover::hover_on_instance_variable(variable))
+        })
+        .or_else(|| {
+            hover::hover_on_word(word, services.gm_manual(), &boss.yy_boss, &boss.symbols)
+        })?;
+
+    if let (Some((object, event)), HoverContents::Array(contents)) =
+        (boss.get_event(uri), &mut hover.contents)
+    {
+        if word == "event_inherited" {
+            contents.push(hover::describe_event_inherited(
+                object,
+                event.event_type,
+                &boss.objects,
+            ));
+        }
+    }
+
+    Some(Hover {
+        range: Some(tree.range(txt, span).into()),
+        ..hover
+    })
+}
+
+/// Makes a rename we've sent the client to our model of the project too. The client tells us
+/// about its edits to the documents it has open with `didChange`, just like any other edit, so
+/// we only make the rest, which it changes on disk without telling us.
+fn apply_rename(
+    mut boss: Boss,
+    rename: &rename::Rename,
+    open_documents: &HashSet<Url>,
+) -> (Boss, Vec<Url>) {
+    for (uri, edits) in &rename.edits {
+        if open_documents.contains(uri) {
+            continue;
+        }
+        if let Some(txt) = boss.get_text_document_mut(uri) {
+            *txt = rename::apply_edits(txt, edits);
+            boss.reparse(uri);
+        } else if let Some(txt) = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| boss.yy_files.get_mut(&path))
+        {
+            *txt = rename::apply_edits(txt, edits);
+        }
+    }
+
+    for operation in &rename.operations {
+        if let ResourceOp::Rename(v) = operation {
+            if let (Ok(from), Ok(to)) = (v.old_uri.to_file_path(), v.new_uri.to_file_path()) {
+                boss.yy_files.rename(&from, &to);
             }
         }
     }
+
+    match &rename.asset {
+        Some(asset) => boss.rename_asset(asset, &rename.new_name),
+        None => (boss, vec![]),
+    }
+}
+
+/// Borrows what the requests which look across the project need.
+fn project<'a>(boss: &'a Boss, services: &'a ServicesProvider) -> Project<'a> {
+    Project {
+        gm_manual: services.gm_manual(),
+        symbols: &boss.symbols,
+        objects: &boss.objects,
+        assets: &boss.assets,
+        documents: boss
+            .gml_documents()
+            .into_iter()
+            .filter_map(|uri| {
+                let document = boss.get_document(&uri)?;
+                Some((uri, document))
+            })
+            .collect(),
+        resources: boss
+            .yy_boss
+            .yyp()
+            .resources
+            .iter()
+            .map(|v| (v.id.name.as_str(), v.id.path.as_str()))
+            .collect(),
+        root_directory: boss.yy_boss.directory_manager.root_directory(),
+        yyp_path: &boss.yyp_path,
+        yy_files: &boss.yy_files,
+    }
+}
+
+/// Runs every diagnostic rule over a gml document.
+fn analyse(
+    boss: &Boss,
+    services: &ServicesProvider,
+    rules: &diagnostics::RuleRegistry,
+    uri: &Url,
+) -> Option<Vec<lsp_types::Diagnostic>> {
+    let (txt, tree) = boss.get_document(uri)?;
+
+    Some(
+        rules.run(&diagnostics::DiagnosticContext {
+            txt,
+            tree,
+            event: boss
+                .get_event(uri)
+                .map(|(object, event)| (object, event.event_type)),
+            objects: &boss.objects,
+            gm_manual: services.gm_manual(),
+            symbols: &boss.symbols,
+            assets: &boss.assets,
+        }),
+    )
+}
+
+/// Reruns the diagnostics for a document, and publishes them if they've changed. Rules like
+/// `undefined` look at the whole project, so a change here can change what's wrong elsewhere, and
+/// every other document we've published for is rerun too.
+fn publish_diagnostics(
+    connection: &Connection,
+    boss: &mut Boss,
+    services: &ServicesProvider,
+    rules: &diagnostics::RuleRegistry,
+    uri: &Url,
+) -> AnyResult<()> {
+    publish_documents(connection, boss, services, rules, std::slice::from_ref(uri))
+}
+
+/// [publish_diagnostics] for several documents at once, like those a rename has edited.
+fn publish_documents(
+    connection: &Connection,
+    boss: &mut Boss,
+    services: &ServicesProvider,
+    rules: &diagnostics::RuleRegistry,
+    uris: &[Url],
+) -> AnyResult<()> {
+    let others = boss
+        .diagnosed_documents()
+        .into_iter()
+        .filter(|v| uris.contains(v) == false);
+    for uri in uris.iter().cloned().chain(others) {
+        let Some(output) = analyse(boss, services, rules, &uri) else {
+            continue;
+        };
+
+        if boss.set_diagnostics(&uri, output.clone()) {
+            send_diagnostics(connection, uri, output)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reruns the diagnostics for a document which the client is pulling. If the client already has
+/// them, from the report with `previous_result_id`, we just tell it they're unchanged.
+fn pull_diagnostics(
+    boss: &mut Boss,
+    services: &ServicesProvider,
+    rules: &diagnostics::RuleRegistry,
+    uri: &Url,
+    previous_result_id: Option<&str>,
+) -> Option<DocumentDiagnosticReportKind> {
+    let output = analyse(boss, services, rules, uri)?;
+    Some(boss.pull_diagnostics(uri, output, previous_result_id))
+}
+
+fn send_diagnostics(
+    connection: &Connection,
+    uri: Url,
+    diagnostics: Vec<lsp_types::Diagnostic>,
+) -> AnyResult<()> {
+    let params = PublishDiagnosticsParams {
+        uri,
+        diagnostics,
+        version: None,
+    };
+    connection
+        .sender
+        .send(Message::Notification(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )))?;
+
     Ok(())
 }
 
@@ -1,5 +1,17 @@
 mod boss;
-pub use boss::Boss;
+pub use boss::{Boss, ResourceLookup, ResourceLookupData};
+
+mod diagnostic_cache;
+pub use diagnostic_cache::*;
 
 mod services_provider;
 pub use services_provider::ServicesProvider;
+
+mod symbol_index;
+pub use symbol_index::*;
+
+mod object_index;
+pub use object_index::*;
+
+mod yy_files;
+pub use yy_files::*;
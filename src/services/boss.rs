@@ -1,27 +1,69 @@
+use std::fmt;
 use std::path::Path;
-use std::{collections::BTreeMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
 
+use lsp_types::{Diagnostic, DocumentDiagnosticReportKind, TextDocumentContentChangeEvent};
 use url::Url;
 use yy_boss::{Resource, YypBoss};
 use yy_boss::{ShaderKind, YyResource};
-use yy_typings::EventType;
+use yy_typings::{EventType, Object, Script, Shader};
 
-use crate::lsp::Position;
+use super::{DiagnosticCache, EventInfo, ObjectIndex, ObjectInfo, SymbolIndex, YyFiles};
+use crate::gml::{Span, SyntaxTree};
+use crate::lsp;
 
 #[derive(Debug)]
 pub struct Boss {
     pub yy_boss: YypBoss,
+    pub yyp_path: PathBuf,
     pub fpaths_to_lookup_data: BTreeMap<PathBuf, ResourceLookup>,
+    pub syntax_trees: BTreeMap<PathBuf, SyntaxTree>,
+    pub symbols: SymbolIndex,
+    pub objects: ObjectIndex,
+    /// The name of every asset in the project, of every kind.
+    pub assets: BTreeSet<String>,
+    pub diagnostics: DiagnosticCache,
+    pub yy_files: YyFiles,
 }
 
 impl Boss {
     pub fn new<P: AsRef<Path>>(path: P) -> Boss {
         let yy_boss = YypBoss::new(
-            path,
+            &path,
             &[Resource::Script, Resource::Object, Resource::Shader],
         )
         .unwrap();
 
+        let root_directory = yy_boss.directory_manager.root_directory();
+        let yy_files = YyFiles::read(
+            yy_boss
+                .yyp()
+                .resources
+                .iter()
+                .map(|v| root_directory.join(&v.id.path))
+                .chain([path.as_ref().to_path_buf()]),
+        );
+
+        Self::index(
+            yy_boss,
+            path.as_ref().to_path_buf(),
+            DiagnosticCache::default(),
+            yy_files,
+        )
+    }
+
+    /// Builds everything we know about the project out of yy-boss's model of it. The diagnostics
+    /// we've reported are kept, since the client still holds their result ids, and so are the
+    /// `.yy` files, which the client may not have saved yet.
+    fn index(
+        yy_boss: YypBoss,
+        yyp_path: PathBuf,
+        diagnostics: DiagnosticCache,
+        yy_files: YyFiles,
+    ) -> Boss {
         let mut fpaths_to_lookup_data = BTreeMap::new();
         let root_directory = yy_boss.directory_manager.root_directory();
 
@@ -44,17 +86,40 @@ impl Boss {
         }
 
         // parse in every event object
+        let mut objects = ObjectIndex::default();
         for object in &yy_boss.objects {
+            let mut info = ObjectInfo::new(
+                object.yy_resource.name().to_owned(),
+                object
+                    .yy_resource
+                    .properties
+                    .iter()
+                    .map(|v| (v.common_data.name.clone(), v.value.clone())),
+            );
+            info.parent = object
+                .yy_resource
+                .parent_object_id
+                .as_ref()
+                .map(|v| v.name.clone());
+
             let path = root_directory.join(object.yy_resource.relative_yy_directory());
             for event in &object.yy_resource.event_list {
+                let event_path = path.join(format!("{}.gml", event.event_type.filename()));
+                info.events.push(EventInfo {
+                    event_type: event.event_type,
+                    path: event_path.clone(),
+                    collision_object: event.collision_object_id.as_ref().map(|v| v.name.clone()),
+                });
+
                 fpaths_to_lookup_data.insert(
-                    path.join(format!("{}.gml", event.event_type.filename())),
+                    event_path,
                     ResourceLookup {
                         name: object.yy_resource.name().to_owned(),
                         data: ResourceLookupData::Object(event.event_type),
                     },
                 );
             }
+            objects.insert(info);
         }
 
         // parse in every shader
@@ -77,116 +142,273 @@ impl Boss {
             }
         }
 
+        // and parse all the gml we found
+        let mut syntax_trees = BTreeMap::new();
+        let mut symbols = SymbolIndex::default();
+        for (path, lookup) in fpaths_to_lookup_data.iter().filter(|(_, v)| v.is_gml()) {
+            if let Some(txt) = Self::lookup_text(&yy_boss, lookup) {
+                let tree = SyntaxTree::parse(txt);
+                symbols.update_file(path, lookup, txt, &tree);
+                objects.update_event(path, lookup, txt, &tree);
+                syntax_trees.insert(path.clone(), tree);
+            }
+        }
+
+        let assets = yy_boss
+            .yyp()
+            .resources
+            .iter()
+            .map(|v| v.id.name.clone())
+            .collect();
+
         Boss {
             yy_boss,
+            yyp_path,
             fpaths_to_lookup_data,
+            syntax_trees,
+            symbols,
+            objects,
+            assets,
+            diagnostics,
+            yy_files,
+        }
+    }
+
+    /// Refreshes the symbols and instance variables a document declares from its current
+    /// syntax tree.
+    fn reindex(&mut self, path: &Path) {
+        let Some(lookup) = self.fpaths_to_lookup_data.get(path) else {
+            return;
+        };
+        let txt = Self::lookup_text(&self.yy_boss, lookup);
+
+        match txt.zip(self.syntax_trees.get(path)) {
+            Some((txt, tree)) => {
+                self.symbols.update_file(path, lookup, txt, tree);
+                self.objects.update_event(path, lookup, txt, tree);
+            }
+            None => self.symbols.remove_file(path),
+        }
+    }
+
+    fn lookup_text<'a>(yy_boss: &'a YypBoss, lookup: &ResourceLookup) -> Option<&'a String> {
+        match &lookup.data {
+            ResourceLookupData::Script => yy_boss
+                .scripts
+                .get(&lookup.name)
+                .and_then(|v| v.associated_data.as_ref()),
+
+            ResourceLookupData::Object(event) => yy_boss
+                .objects
+                .get(&lookup.name)
+                .and_then(|v| v.associated_data.as_ref().and_then(|v| v.get(event))),
+            ResourceLookupData::Shader(shad_kind) => yy_boss
+                .shaders
+                .get(&lookup.name)
+                .and_then(|v| v.associated_data.as_ref().map(|v| &v[*shad_kind])),
+        }
+    }
+
+    pub fn get_lookup(&self, url: &Url) -> Option<&ResourceLookup> {
+        self.fpaths_to_lookup_data.get(&url.to_file_path().unwrap())
+    }
+
+    /// The object and event a document is the code of, if it's an event.
+    pub fn get_event(&self, url: &Url) -> Option<(&ObjectInfo, &EventInfo)> {
+        let lookup = self.get_lookup(url)?;
+        match lookup.data {
+            ResourceLookupData::Object(_) => {
+                let object = self.objects.get(&lookup.name)?;
+                let event = object.event_at(&url.to_file_path().unwrap())?;
+                Some((object, event))
+            }
+            _ => None,
         }
     }
 
     pub fn get_text_document(&self, url: &Url) -> Option<&String> {
         self.fpaths_to_lookup_data
             .get(&url.to_file_path().unwrap())
-            .and_then(|v| match &v.data {
-                ResourceLookupData::Script => self
-                    .yy_boss
-                    .scripts
-                    .get(&v.name)
-                    .and_then(|v| v.associated_data.as_ref()),
+            .and_then(|v| Self::lookup_text(&self.yy_boss, v))
+    }
 
-                ResourceLookupData::Object(event) => self
-                    .yy_boss
-                    .objects
-                    .get(&v.name)
-                    .and_then(|v| v.associated_data.as_ref().and_then(|v| v.get(event))),
-                ResourceLookupData::Shader(shad_kind) => self
-                    .yy_boss
-                    .shaders
-                    .get(&v.name)
-                    .and_then(|v| v.associated_data.as_ref().map(|v| &v[*shad_kind])),
-            })
+    pub fn get_syntax_tree(&self, url: &Url) -> Option<&SyntaxTree> {
+        self.syntax_trees.get(&url.to_file_path().unwrap())
     }
 
-    pub fn get_text_document_mut(&mut self, url: &Url) -> Option<&mut String> {
-        unsafe {
-            if let Some(v) = self.fpaths_to_lookup_data.get(&url.to_file_path().unwrap()) {
-                match &v.data {
-                    ResourceLookupData::Script => self
-                        .yy_boss
-                        .scripts
-                        .get_mut(&v.name)
-                        .and_then(|v| v.associated_data.as_mut()),
-
-                    ResourceLookupData::Object(event) => self
-                        .yy_boss
-                        .objects
-                        .get_mut(&v.name)
-                        .and_then(|v| v.associated_data.as_mut().and_then(|v| v.get_mut(event))),
-                    ResourceLookupData::Shader(shad_kind) => self
-                        .yy_boss
-                        .shaders
-                        .get_mut(&v.name)
-                        .and_then(|v| v.associated_data.as_mut().map(|v| &mut v[*shad_kind])),
-                }
-            } else {
-                None
-            }
-        }
+    /// Returns the text of a gml document along with its syntax tree.
+    pub fn get_document(&self, url: &Url) -> Option<(&str, &SyntaxTree)> {
+        self.get_text_document(url)
+            .zip(self.get_syntax_tree(url))
+            .map(|(txt, tree)| (txt.as_str(), tree))
     }
 
-    pub fn get_word_in_document<P: Into<Position>>(txt: &str, pos: P) -> Option<&str> {
-        let pos: Position = pos.into();
+    /// Caches a document's diagnostics. Returns false if they're the same as last time, so
+    /// there's no need to publish them again.
+    pub fn set_diagnostics(&mut self, url: &Url, diagnostics: Vec<Diagnostic>) -> bool {
+        self.diagnostics
+            .set(&url.to_file_path().unwrap(), diagnostics)
+    }
 
-        txt.lines().nth(pos.line).map(|line| {
-            // find the last whitespace...
-            let mut start = 0;
-            for (i, chr) in line.char_indices() {
-                if i == pos.column {
-                    break;
-                }
+    /// Caches a document's diagnostics for a client which is pulling them, and reports them
+    /// unless the client already has them.
+    pub fn pull_diagnostics(
+        &mut self,
+        url: &Url,
+        diagnostics: Vec<Diagnostic>,
+        previous_result_id: Option<&str>,
+    ) -> DocumentDiagnosticReportKind {
+        self.diagnostics.pull(
+            &url.to_file_path().unwrap(),
+            diagnostics,
+            previous_result_id,
+        )
+    }
 
-                if !(chr.is_ascii_alphanumeric() || chr == '_') {
-                    start = i + 1;
-                }
-            }
+    /// Every document we have diagnostics for.
+    pub fn diagnosed_documents(&self) -> Vec<Url> {
+        self.diagnostics
+            .paths()
+            .filter_map(|v| Url::from_file_path(v).ok())
+            .collect()
+    }
+
+    /// Renames an asset in yy-boss's model of the project, to match the edit we've sent the
+    /// client, and reindexes everything. Only the kinds of asset we load need it; the rest are
+    /// just names to us. Also returns the documents which have moved away, whose diagnostics
+    /// we've forgotten.
+    pub fn rename_asset(mut self, name: &str, new_name: &str) -> (Boss, Vec<Url>) {
+        let output = if self.yy_boss.objects.get(name).is_some() {
+            self.yy_boss
+                .rename_resource::<Object>(name, new_name.to_string())
+        } else if self.yy_boss.scripts.get(name).is_some() {
+            self.yy_boss
+                .rename_resource::<Script>(name, new_name.to_string())
+        } else if self.yy_boss.shaders.get(name).is_some() {
+            self.yy_boss
+                .rename_resource::<Shader>(name, new_name.to_string())
+        } else {
+            Ok(())
+        };
+        if let Err(e) = output {
+            log::error!("couldn't rename {} to {}: {:?}", name, new_name, e);
+        }
+
+        let mut boss = Self::index(self.yy_boss, self.yyp_path, self.diagnostics, self.yy_files);
+        // the asset's documents have moved, so what we had for them under the old paths is gone
+        let documents = &boss.fpaths_to_lookup_data;
+        let moved = boss
+            .diagnostics
+            .retain(|path| documents.contains_key(path))
+            .into_iter()
+            .filter_map(|v| Url::from_file_path(v).ok())
+            .collect();
+        // yy-boss doesn't load every kind of asset, so the yyp can still have the old name
+        if boss.assets.remove(name) {
+            boss.assets.insert(new_name.to_string());
+        }
+
+        (boss, moved)
+    }
 
-            // make sure we're not on the last of the line...
-            start = pos.column.min(start);
+    /// Every script and event in the project.
+    pub fn gml_documents(&self) -> Vec<Url> {
+        self.fpaths_to_lookup_data
+            .iter()
+            .filter(|(_, v)| v.is_gml())
+            .filter_map(|(path, _)| Url::from_file_path(path).ok())
+            .collect()
+    }
 
-            // FINALLY, the GLORIOUS word is here...
-            &line[start..pos.column]
-        })
+    /// Forgets a document's diagnostics when it's closed. Returns false if it had none.
+    pub fn clear_diagnostics(&mut self, url: &Url) -> bool {
+        self.diagnostics.clear(&url.to_file_path().unwrap())
     }
 
-    pub fn get_word_in_document_full<P: Into<Position>>(txt: &str, pos: P) -> Option<&str> {
-        let pos: Position = pos.into();
+    /// Rebuilds the syntax tree of a document after its text has changed.
+    pub fn reparse(&mut self, url: &Url) {
+        let path = url.to_file_path().unwrap();
+        let Some(lookup) = self.fpaths_to_lookup_data.get(&path) else {
+            return;
+        };
+        if lookup.is_gml() == false {
+            return;
+        }
 
-        txt.lines().nth(pos.line).map(|line| {
-            // find the last whitespace...
-            let mut start = 0;
-            let mut end = line.len();
+        if let Some(txt) = Self::lookup_text(&self.yy_boss, lookup) {
+            self.syntax_trees
+                .insert(path.clone(), SyntaxTree::parse(txt));
+        }
+        self.reindex(&path);
+    }
 
-            let mut hit_end = false;
-            for (i, chr) in line.char_indices() {
-                if !(chr.is_ascii_alphanumeric() || chr == '_') {
-                    if hit_end {
-                        end = i;
-                        break;
-                    } else {
-                        start = i + 1;
-                    }
-                }
+    fn lookup_text_mut<'a>(
+        yy_boss: &'a mut YypBoss,
+        lookup: &ResourceLookup,
+    ) -> Option<&'a mut String> {
+        unsafe {
+            match &lookup.data {
+                ResourceLookupData::Script => yy_boss
+                    .scripts
+                    .get_mut(&lookup.name)
+                    .and_then(|v| v.associated_data.as_mut()),
 
-                if i == pos.column {
-                    hit_end = true;
-                }
+                ResourceLookupData::Object(event) => yy_boss
+                    .objects
+                    .get_mut(&lookup.name)
+                    .and_then(|v| v.associated_data.as_mut().and_then(|v| v.get_mut(event))),
+                ResourceLookupData::Shader(shad_kind) => yy_boss
+                    .shaders
+                    .get_mut(&lookup.name)
+                    .and_then(|v| v.associated_data.as_mut().map(|v| &mut v[*shad_kind])),
             }
+        }
+    }
 
-            // make sure we're not on the last of the line...
-            start = end.min(start);
+    pub fn get_text_document_mut(&mut self, url: &Url) -> Option<&mut String> {
+        self.fpaths_to_lookup_data
+            .get(&url.to_file_path().unwrap())
+            .and_then(|v| Self::lookup_text_mut(&mut self.yy_boss, v))
+    }
+
+    /// Applies a change from `DidChangeTextDocument` to a document's text, and updates its
+    /// syntax tree to match. Ranged changes only reparse the part of the tree they touch.
+    /// Returns false if we don't know the document.
+    pub fn apply_change(&mut self, url: &Url, change: TextDocumentContentChangeEvent) -> bool {
+        let path = url.to_file_path().unwrap();
+        let Some(lookup) = self.fpaths_to_lookup_data.get(&path) else {
+            return false;
+        };
+        let Some(txt) = Self::lookup_text_mut(&mut self.yy_boss, lookup) else {
+            return false;
+        };
+
+        let Some(range) = change.range else {
+            *txt = change.text;
+            if lookup.is_gml() {
+                self.syntax_trees
+                    .insert(path.clone(), SyntaxTree::parse(txt));
+                self.reindex(&path);
+            }
+            return true;
+        };
+
+        let range: lsp::Range = range.into();
+        let start = range.start.get_idx(txt).unwrap();
+        let end = range.end.get_idx(txt).unwrap();
+        txt.replace_range(start..end, &change.text);
+
+        match self.syntax_trees.get_mut(&path) {
+            Some(tree) => tree.edit(txt, Span::new(start, end), change.text.len()),
+            None if lookup.is_gml() => {
+                self.syntax_trees
+                    .insert(path.clone(), SyntaxTree::parse(txt));
+            }
+            None => return true,
+        }
+        self.reindex(&path);
 
-            // FINALLY, the GLORIOUS word is here...
-            &line[start..end]
-        })
+        true
     }
 }
 
@@ -202,3 +424,24 @@ pub struct ResourceLookup {
     pub name: String,
     pub data: ResourceLookupData,
 }
+
+impl ResourceLookup {
+    /// Shaders are tracked too, but they aren't gml, so we don't parse them.
+    pub fn is_gml(&self) -> bool {
+        matches!(
+            self.data,
+            ResourceLookupData::Script | ResourceLookupData::Object(_)
+        )
+    }
+}
+
+impl fmt::Display for ResourceLookup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.data {
+            ResourceLookupData::Object(event) => write!(f, "{} ({})", self.name, event.filename()),
+            ResourceLookupData::Script | ResourceLookupData::Shader(_) => {
+                write!(f, "{}", self.name)
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use lsp_types::{
+    Diagnostic, DocumentDiagnosticReportKind, FullDocumentDiagnosticReport,
+    UnchangedDocumentDiagnosticReport,
+};
+
+/// The diagnostics we last reported for each document, along with the id the client knows them
+/// by, so that it can ask whether they've changed since. Ids are never reused, since a client
+/// which still holds an old one would be told its stale diagnostics are current.
+#[derive(Debug, Default)]
+pub struct DiagnosticCache {
+    reports: BTreeMap<PathBuf, DiagnosticReport>,
+    next_result_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticReport {
+    pub result_id: String,
+    pub items: Vec<Diagnostic>,
+}
+
+impl DiagnosticCache {
+    /// Caches a document's diagnostics, giving them a new result id if they've changed. Returns
+    /// false if they're the same as last time, so there's no need to publish them again.
+    pub fn set(&mut self, path: &Path, diagnostics: Vec<Diagnostic>) -> bool {
+        if self
+            .reports
+            .get(path)
+            .is_some_and(|v| v.items == diagnostics)
+        {
+            return false;
+        }
+
+        self.next_result_id += 1;
+        self.reports.insert(
+            path.to_path_buf(),
+            DiagnosticReport {
+                result_id: self.next_result_id.to_string(),
+                items: diagnostics,
+            },
+        );
+
+        true
+    }
+
+    /// Forgets a document's diagnostics. Returns false if it had none.
+    pub fn clear(&mut self, path: &Path) -> bool {
+        self.reports
+            .remove(path)
+            .is_some_and(|v| v.items.is_empty() == false)
+    }
+
+    /// Forgets the diagnostics of every document which `keep` rejects, like those of an asset
+    /// which has been renamed, while still never reusing a result id. Returns the documents
+    /// which were forgotten, since the client still shows what we last told it about them.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Path) -> bool) -> Vec<PathBuf> {
+        let forgotten: Vec<PathBuf> = self
+            .reports
+            .keys()
+            .filter(|v| keep(v) == false)
+            .cloned()
+            .collect();
+        for path in &forgotten {
+            self.reports.remove(path);
+        }
+
+        forgotten
+    }
+
+    /// Every document we have diagnostics for.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.reports.keys().map(PathBuf::as_path)
+    }
+
+    /// Caches a document's diagnostics for a client which is pulling them. If the client already
+    /// has them, from the report with `previous_result_id`, we just tell it they're unchanged.
+    pub fn pull(
+        &mut self,
+        path: &Path,
+        diagnostics: Vec<Diagnostic>,
+        previous_result_id: Option<&str>,
+    ) -> DocumentDiagnosticReportKind {
+        self.set(path, diagnostics);
+        let report = &self.reports[path];
+
+        if previous_result_id == Some(report.result_id.as_str()) {
+            return DocumentDiagnosticReportKind::Unchanged(UnchangedDocumentDiagnosticReport {
+                result_id: report.result_id.clone(),
+            });
+        }
+
+        DocumentDiagnosticReportKind::Full(FullDocumentDiagnosticReport {
+            result_id: Some(report.result_id.clone()),
+            items: report.items.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn result_ids() {
+        let path = Path::new("/project/scripts/scr_a/scr_a.gml");
+        let diagnostic = |message: &str| Diagnostic {
+            message: message.to_string(),
+            ..Diagnostic::default()
+        };
+        let mut cache = DiagnosticCache::default();
+
+        let DocumentDiagnosticReportKind::Full(first) =
+            cache.pull(path, vec![diagnostic("a")], None)
+        else {
+            panic!("expected a full report");
+        };
+        assert_eq!(first.items, vec![diagnostic("a")]);
+        let first_id = first.result_id.unwrap();
+
+        assert_eq!(
+            cache.pull(path, vec![diagnostic("a")], Some(&first_id)),
+            DocumentDiagnosticReportKind::Unchanged(UnchangedDocumentDiagnosticReport {
+                result_id: first_id.clone(),
+            })
+        );
+
+        let DocumentDiagnosticReportKind::Full(changed) =
+            cache.pull(path, vec![diagnostic("b")], Some(&first_id))
+        else {
+            panic!("expected a full report after a change");
+        };
+        assert_eq!(changed.items, vec![diagnostic("b")]);
+        let changed_id = changed.result_id.unwrap();
+        assert_ne!(changed_id, first_id);
+
+        assert!(cache.clear(path));
+        let DocumentDiagnosticReportKind::Full(cleared) =
+            cache.pull(path, vec![diagnostic("b")], Some(&changed_id))
+        else {
+            panic!("expected a full report after clearing");
+        };
+        assert_ne!(cleared.result_id.unwrap(), changed_id);
+    }
+
+    #[test]
+    fn result_ids_across_rename() {
+        let old_path = Path::new("/project/scripts/scr_a/scr_a.gml");
+        let new_path = Path::new("/project/scripts/scr_b/scr_b.gml");
+        let other_path = Path::new("/project/scripts/scr_c/scr_c.gml");
+        let result_id = |report: DocumentDiagnosticReportKind| match report {
+            DocumentDiagnosticReportKind::Full(v) => v.result_id.unwrap(),
+            DocumentDiagnosticReportKind::Unchanged(v) => v.result_id,
+        };
+        let mut cache = DiagnosticCache::default();
+
+        let old_id = result_id(cache.pull(old_path, vec![], None));
+        let other_id = result_id(cache.pull(other_path, vec![], None));
+
+        // renaming scr_a to scr_b moves its document
+        assert_eq!(
+            cache.retain(|path| path != old_path),
+            vec![old_path.to_path_buf()]
+        );
+        assert_eq!(cache.paths().collect::<Vec<_>>(), vec![other_path]);
+
+        let DocumentDiagnosticReportKind::Full(renamed) =
+            cache.pull(new_path, vec![], Some(&old_id))
+        else {
+            panic!("expected a full report for the renamed document");
+        };
+        let renamed_id = renamed.result_id.unwrap();
+        assert_ne!(renamed_id, old_id);
+        assert_ne!(renamed_id, other_id);
+
+        assert_eq!(
+            cache.pull(other_path, vec![], Some(&other_id)),
+            DocumentDiagnosticReportKind::Unchanged(UnchangedDocumentDiagnosticReport {
+                result_id: other_id,
+            })
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use yy_typings::EventType;
+
+use super::boss::{ResourceLookup, ResourceLookupData};
+use crate::{gml::*, lsp::Range};
+
+/// What we know about each object in the project, by name.
+#[derive(Debug, Default)]
+pub struct ObjectIndex {
+    objects: BTreeMap<String, ObjectInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ObjectInfo {
+    pub name: String,
+    pub parent: Option<String>,
+    /// The events this object defines itself.
+    pub events: Vec<EventInfo>,
+    /// The Object Variables from the object's `.yy` file.
+    pub properties: Vec<InstanceVariable>,
+    /// The variables the Create event assigns to.
+    pub create_variables: Vec<InstanceVariable>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventInfo {
+    pub event_type: EventType,
+    pub path: PathBuf,
+    /// The object a collision event is with, which is `other` inside of it.
+    pub collision_object: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceVariable {
+    pub name: String,
+    pub object: String,
+    pub origin: VariableOrigin,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariableOrigin {
+    /// Assigned to in one of the object's events.
+    Event {
+        event: EventType,
+        path: PathBuf,
+        span: Span,
+        range: Range,
+    },
+    /// An Object Variable, with its default value as written in the IDE.
+    Property { value: String },
+}
+
+impl ObjectInfo {
+    pub fn new(name: String, properties: impl IntoIterator<Item = (String, String)>) -> Self {
+        let properties = properties
+            .into_iter()
+            .map(|(var_name, value)| InstanceVariable {
+                name: var_name,
+                object: name.clone(),
+                origin: VariableOrigin::Property { value },
+            })
+            .collect();
+
+        Self {
+            name,
+            properties,
+            ..Self::default()
+        }
+    }
+
+    pub fn event_path(&self, event: EventType) -> Option<&Path> {
+        self.events
+            .iter()
+            .find(|v| v.event_type == event)
+            .map(|v| v.path.as_path())
+    }
+
+    /// Finds the event whose code is in the `.gml` file at `path`.
+    pub fn event_at(&self, path: &Path) -> Option<&EventInfo> {
+        self.events.iter().find(|v| v.path == path)
+    }
+
+    /// The variables this object declares, Object Variables first.
+    pub fn variables(&self) -> impl Iterator<Item = &InstanceVariable> {
+        self.properties.iter().chain(&self.create_variables)
+    }
+}
+
+impl ObjectIndex {
+    pub fn insert(&mut self, object: ObjectInfo) {
+        self.objects.insert(object.name.clone(), object);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ObjectInfo> {
+        self.objects.get(name)
+    }
+
+    /// The parent of `object`, then its parent, and so on up the chain.
+    pub fn ancestors(&self, object: &str) -> Vec<&ObjectInfo> {
+        let mut output: Vec<&ObjectInfo> = vec![];
+        let mut next = self.get(object).and_then(|v| v.parent.as_deref());
+
+        while let Some(parent) = next.and_then(|v| self.get(v)) {
+            // the IDE won't make a cycle, but a hand edited `.yy` could
+            if parent.name == object || output.iter().any(|v| v.name == parent.name) {
+                break;
+            }
+            output.push(parent);
+            next = parent.parent.as_deref();
+        }
+
+        output
+    }
+
+    /// Every variable which instances of `object` have, including those declared by its
+    /// ancestors. Where a child redeclares a variable, the child's declaration wins.
+    pub fn variables(&self, object: &str) -> Vec<&InstanceVariable> {
+        let mut output: Vec<&InstanceVariable> = vec![];
+        let objects = self.get(object).into_iter().chain(self.ancestors(object));
+        for variable in objects.flat_map(ObjectInfo::variables) {
+            if output.iter().any(|v| v.name == variable.name) == false {
+                output.push(variable);
+            }
+        }
+
+        output
+    }
+
+    /// Finds the ancestor whose `event` runs when `object` calls `event_inherited()` in it.
+    pub fn inherited_event(&self, object: &str, event: EventType) -> Option<(&ObjectInfo, &Path)> {
+        self.ancestors(object)
+            .into_iter()
+            .find_map(|v| v.event_path(event).map(|path| (v, path)))
+    }
+
+    /// Finds a variable which instances of `object` have.
+    pub fn variable(&self, object: &str, name: &str) -> Option<&InstanceVariable> {
+        self.variables(object).into_iter().find(|v| v.name == name)
+    }
+
+    /// Whether any object in the project declares a variable. Code in scripts and `with` blocks
+    /// can be run by any instance, so this is the best we can do for them.
+    pub fn any_declares(&self, name: &str) -> bool {
+        self.objects
+            .values()
+            .flat_map(ObjectInfo::variables)
+            .any(|v| v.name == name)
+    }
+
+    /// Re-collects what an event declares after it has been reparsed.
+    pub fn update_event(
+        &mut self,
+        path: &Path,
+        source: &ResourceLookup,
+        txt: &str,
+        tree: &SyntaxTree,
+    ) {
+        let ResourceLookupData::Object(EventType::Create) = source.data else {
+            return;
+        };
+        let Some(object) = self.objects.get_mut(&source.name) else {
+            return;
+        };
+
+        let mut collector = CreateCollector {
+            locals: vec![],
+            assigned: vec![],
+        };
+        for stmt in &tree.stmts {
+            collector.visit_stmt(stmt);
+        }
+
+        let mut variables: Vec<InstanceVariable> = vec![];
+        for ident in collector.assigned {
+            let is_local = collector.locals.contains(&ident.name.as_str());
+            let is_duplicate = variables.iter().any(|v| v.name == ident.name);
+            if is_local || is_duplicate {
+                continue;
+            }
+
+            variables.push(InstanceVariable {
+                name: ident.name.clone(),
+                object: source.name.clone(),
+                origin: VariableOrigin::Event {
+                    event: EventType::Create,
+                    path: path.to_owned(),
+                    span: ident.span,
+                    range: tree.range(txt, ident.span),
+                },
+            });
+        }
+
+        object.create_variables = variables;
+    }
+}
+
+/// Finds the instance variables a Create event assigns to.
+struct CreateCollector<'a> {
+    locals: Vec<&'a str>,
+    assigned: Vec<&'a Ident>,
+}
+
+impl<'a> Visitor<'a> for CreateCollector<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match &stmt.kind {
+            StmtKind::Var(decls) => {
+                self.locals
+                    .extend(decls.iter().map(|v| v.name.name.as_str()));
+            }
+            StmtKind::Assign {
+                target,
+                op: AssignOp::Assign,
+                ..
+            } => match &target.kind {
+                ExprKind::Ident(ident) => self.assigned.push(ident),
+                ExprKind::Field { expr, field } => {
+                    let is_self = matches!(&expr.kind, ExprKind::Ident(v) if v.name == "self");
+                    if is_self && field.name.is_empty() == false {
+                        self.assigned.push(field);
+                    }
+                }
+                _ => {}
+            },
+            // assignments in here belong to some other instance
+            StmtKind::With { .. } => return,
+            _ => {}
+        }
+
+        walk_stmt(self, stmt);
+    }
+
+    // assignments in methods and constructors only happen when they're called, if ever
+    fn visit_function(&mut self, _: &'a FunctionDecl) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_variables() {
+        let mut index = ObjectIndex::default();
+        index.insert(ObjectInfo::new(
+            "obj_player".to_string(),
+            [("max_hp".to_string(), "100".to_string())],
+        ));
+
+        let txt = r#"
+hp = max_hp;
+var i = 0;
+i = 2;
+self.name = "player";
+if (hp > 0) { alive = true; }
+with (obj_enemy) { target = other; }
+reset = function() { speed_mult = 1; }
+hp = 5;
+"#;
+        let source = ResourceLookup {
+            name: "obj_player".to_string(),
+            data: ResourceLookupData::Object(EventType::Create),
+        };
+        let path = Path::new("Create_0.gml");
+        index.update_event(path, &source, txt, &SyntaxTree::parse(txt));
+
+        let names: Vec<_> = index
+            .get("obj_player")
+            .unwrap()
+            .variables()
+            .map(|v| v.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["max_hp", "hp", "name", "alive", "reset"]);
+
+        let VariableOrigin::Event { range, .. } =
+            &index.variable("obj_player", "hp").unwrap().origin
+        else {
+            panic!("expected an event variable");
+        };
+        assert_eq!(range.start.line, 1);
+    }
+
+    #[test]
+    fn inheritance() {
+        let mut index = ObjectIndex::default();
+        let object = |name: &str, parent: Option<&str>, events: &[EventType], vars: &[&str]| {
+            let mut info = ObjectInfo::new(
+                name.to_string(),
+                vars.iter().map(|v| (v.to_string(), "0".to_string())),
+            );
+            info.parent = parent.map(str::to_string);
+            info.events = events
+                .iter()
+                .map(|v| EventInfo {
+                    event_type: *v,
+                    path: PathBuf::from(format!("{}.gml", name)),
+                    collision_object: None,
+                })
+                .collect();
+            info
+        };
+        index.insert(object("obj_base", None, &[EventType::Create], &["hp"]));
+        index.insert(object("obj_enemy", Some("obj_base"), &[], &["damage"]));
+        index.insert(object(
+            "obj_slime",
+            Some("obj_enemy"),
+            &[EventType::Create],
+            &["hp", "bounce"],
+        ));
+        index.insert(object("obj_loop", Some("obj_loop"), &[], &[]));
+
+        let ancestors: Vec<_> = index
+            .ancestors("obj_slime")
+            .into_iter()
+            .map(|v| v.name.as_str())
+            .collect();
+        assert_eq!(ancestors, vec!["obj_enemy", "obj_base"]);
+        assert!(index.ancestors("obj_loop").is_empty());
+
+        let variables: Vec<_> = index
+            .variables("obj_slime")
+            .into_iter()
+            .map(|v| (v.name.as_str(), v.object.as_str()))
+            .collect();
+        assert_eq!(
+            variables,
+            vec![
+                ("hp", "obj_slime"),
+                ("bounce", "obj_slime"),
+                ("damage", "obj_enemy")
+            ]
+        );
+
+        let (parent, path) = index
+            .inherited_event("obj_slime", EventType::Create)
+            .unwrap();
+        assert_eq!(parent.name, "obj_base");
+        assert_eq!(path, Path::new("obj_base.gml"));
+        assert!(index
+            .inherited_event("obj_slime", EventType::Destroy)
+            .is_none());
+    }
+}
@@ -0,0 +1,529 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+
+use super::boss::{ResourceLookup, ResourceLookupData};
+use crate::{gml::*, lsp::Range};
+
+/// Something a user declared which can be seen from anywhere in the project.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub path: PathBuf,
+    pub source: ResourceLookup,
+    /// The span of the name at the declaration.
+    pub span: Span,
+    pub range: Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolKind {
+    /// A function declared at the top level of a script.
+    Function(FunctionSymbol),
+    Macro(MacroSymbol),
+    Enum(EnumSymbol),
+    /// A `globalvar`, or an assignment to `global.name`.
+    GlobalVariable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionSymbol {
+    pub params: Vec<ParamSymbol>,
+    pub constructor: bool,
+    pub docs: JsDoc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamSymbol {
+    pub name: String,
+    /// The default value, as written.
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroSymbol {
+    pub config: Option<String>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumSymbol {
+    pub members: Vec<EnumMemberSymbol>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumMemberSymbol {
+    pub name: String,
+    /// `None` when the initialiser isn't something we can evaluate.
+    pub value: Option<i64>,
+    pub span: Span,
+    pub range: Range,
+}
+
+impl EnumSymbol {
+    pub fn member(&self, name: &str) -> Option<&EnumMemberSymbol> {
+        self.members.iter().find(|v| v.name == name)
+    }
+}
+
+impl MacroSymbol {
+    /// The text the macro expands to, with line continuations removed.
+    pub fn expansion(&self) -> String {
+        self.body
+            .lines()
+            .map(|line| line.trim_end().trim_end_matches('\\').trim_end())
+            .join("\n")
+    }
+}
+
+impl Symbol {
+    /// A one line summary of the declaration, used for completion details and hover headers.
+    pub fn detail(&self) -> String {
+        match &self.kind {
+            SymbolKind::Function(func) => {
+                let params = func.params.iter().map(|param| match &param.default {
+                    Some(default) => format!("{} = {}", param.name, default),
+                    None => param.name.clone(),
+                });
+                let constructor = if func.constructor { " constructor" } else { "" };
+
+                format!(
+                    "function {}({}){}",
+                    self.name,
+                    params.format(", "),
+                    constructor
+                )
+            }
+            SymbolKind::Macro(mac) => match &mac.config {
+                Some(config) => format!("#macro {}:{} {}", config, self.name, mac.body),
+                None => format!("#macro {} {}", self.name, mac.body),
+            },
+            SymbolKind::Enum(decl) => {
+                format!(
+                    "enum {} {{ {} }}",
+                    self.name,
+                    decl.members.iter().map(|v| &v.name).format(", ")
+                )
+            }
+            SymbolKind::GlobalVariable => format!("global.{}", self.name),
+        }
+    }
+}
+
+/// Every [Symbol] in the project, by name. Each document's symbols are replaced wholesale
+/// whenever that document is reparsed.
+#[derive(Debug, Default)]
+pub struct SymbolIndex {
+    symbols: BTreeMap<String, Vec<Symbol>>,
+    files: BTreeMap<PathBuf, Vec<String>>,
+}
+
+impl SymbolIndex {
+    pub fn get(&self, name: &str) -> &[Symbol] {
+        self.symbols
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Iterates over every name, along with all the places it's declared.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[Symbol])> {
+        self.symbols.iter().map(|(k, v)| (k.as_str(), v.as_slice()))
+    }
+
+    pub fn remove_file(&mut self, path: &Path) {
+        for name in self.files.remove(path).unwrap_or_default() {
+            if let Some(symbols) = self.symbols.get_mut(&name) {
+                symbols.retain(|v| v.path != path);
+                if symbols.is_empty() {
+                    self.symbols.remove(&name);
+                }
+            }
+        }
+    }
+
+    pub fn update_file(
+        &mut self,
+        path: &Path,
+        source: &ResourceLookup,
+        txt: &str,
+        tree: &SyntaxTree,
+    ) {
+        self.remove_file(path);
+
+        let mut collector = SymbolCollector {
+            path,
+            source,
+            txt,
+            tree,
+            symbols: vec![],
+        };
+        collector.collect();
+
+        let names = collector.symbols.iter().map(|v| v.name.clone()).collect();
+        for symbol in collector.symbols {
+            self.symbols
+                .entry(symbol.name.clone())
+                .or_default()
+                .push(symbol);
+        }
+        self.files.insert(path.to_owned(), names);
+    }
+}
+
+struct SymbolCollector<'a> {
+    path: &'a Path,
+    source: &'a ResourceLookup,
+    txt: &'a str,
+    tree: &'a SyntaxTree,
+    symbols: Vec<Symbol>,
+}
+
+impl<'a> SymbolCollector<'a> {
+    fn collect(&mut self) {
+        // only functions at the top of a script are global. In an object event, they're methods.
+        if self.source.data == ResourceLookupData::Script {
+            for stmt in &self.tree.stmts {
+                match &stmt.kind {
+                    StmtKind::Function(func) => {
+                        if let Some(name) = &func.name {
+                            self.push_function(name, func, stmt.span.start);
+                        }
+                    }
+                    StmtKind::Assign {
+                        target,
+                        op: AssignOp::Assign,
+                        value:
+                            Expr {
+                                kind: ExprKind::Function(func),
+                                ..
+                            },
+                    } => {
+                        if let ExprKind::Ident(name) = &target.kind {
+                            self.push_function(name, func, stmt.span.start);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for stmt in &self.tree.stmts {
+            self.visit_stmt(stmt);
+        }
+    }
+
+    fn push(&mut self, name: &Ident, kind: SymbolKind) {
+        self.symbols.push(Symbol {
+            name: name.name.clone(),
+            kind,
+            path: self.path.to_owned(),
+            source: self.source.clone(),
+            span: name.span,
+            range: self.tree.range(self.txt, name.span),
+        });
+    }
+
+    /// Pushes a function, along with the doc comment above `start`.
+    fn push_function(&mut self, name: &Ident, func: &FunctionDecl, start: usize) {
+        let params = func
+            .params
+            .iter()
+            .map(|param| ParamSymbol {
+                name: param.name.name.clone(),
+                default: param
+                    .default
+                    .as_ref()
+                    .map(|v| self.txt[v.span.start..v.span.end].to_string()),
+            })
+            .collect();
+
+        self.push(
+            name,
+            SymbolKind::Function(FunctionSymbol {
+                params,
+                constructor: func.constructor,
+                docs: self
+                    .tree
+                    .doc_comment_before(self.txt, start)
+                    .unwrap_or_default(),
+            }),
+        );
+    }
+}
+
+impl<'a> Visitor<'a> for SymbolCollector<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match &stmt.kind {
+            StmtKind::Macro(decl) => self.push(
+                &decl.name,
+                SymbolKind::Macro(MacroSymbol {
+                    config: decl.config.as_ref().map(|v| v.name.clone()),
+                    body: decl.body.clone(),
+                }),
+            ),
+            StmtKind::Enum(decl) => {
+                let mut members: Vec<EnumMemberSymbol> = vec![];
+                for member in &decl.members {
+                    // members without an initialiser count up from the one before
+                    let value = match &member.value {
+                        Some(value) => eval_enum_value(&decl.name.name, &members, value),
+                        None => match members.last() {
+                            Some(previous) => previous.value.map(|v| v + 1),
+                            None => Some(0),
+                        },
+                    };
+
+                    members.push(EnumMemberSymbol {
+                        name: member.name.name.clone(),
+                        value,
+                        span: member.name.span,
+                        range: self.tree.range(self.txt, member.name.span),
+                    });
+                }
+
+                self.push(&decl.name, SymbolKind::Enum(EnumSymbol { members }));
+            }
+            StmtKind::GlobalVar(names) => {
+                for name in names {
+                    self.push(name, SymbolKind::GlobalVariable);
+                }
+            }
+            StmtKind::Assign {
+                target,
+                op: AssignOp::Assign,
+                ..
+            } => {
+                if let ExprKind::Field { expr, field } = &target.kind {
+                    let is_global = matches!(&expr.kind, ExprKind::Ident(v) if v.name == "global");
+                    if is_global && field.name.is_empty() == false {
+                        self.push(field, SymbolKind::GlobalVariable);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        walk_stmt(self, stmt);
+    }
+}
+
+/// Evaluates an enum member's initialiser. These are integer constant expressions, which can
+/// refer to the members declared before them.
+fn eval_enum_value(enum_name: &str, members: &[EnumMemberSymbol], expr: &Expr) -> Option<i64> {
+    let eval = |expr: &Expr| eval_enum_value(enum_name, members, expr);
+
+    match &expr.kind {
+        ExprKind::Number(lexeme) => parse_integer(lexeme),
+        ExprKind::Grouping(expr) => eval(expr),
+        ExprKind::Unary { op, expr } => {
+            let value = eval(expr)?;
+            match op {
+                UnaryOp::Negate => value.checked_neg(),
+                UnaryOp::Plus => Some(value),
+                UnaryOp::BitNot => Some(!value),
+                UnaryOp::Not | UnaryOp::PreIncrement | UnaryOp::PreDecrement => None,
+            }
+        }
+        ExprKind::Binary { op, lhs, rhs } => {
+            let lhs = eval(lhs)?;
+            let rhs = eval(rhs)?;
+            match op {
+                BinaryOp::Add => lhs.checked_add(rhs),
+                BinaryOp::Subtract => lhs.checked_sub(rhs),
+                BinaryOp::Multiply => lhs.checked_mul(rhs),
+                BinaryOp::Divide | BinaryOp::IntDivide => lhs.checked_div(rhs),
+                BinaryOp::Modulo => lhs.checked_rem(rhs),
+                BinaryOp::BitAnd => Some(lhs & rhs),
+                BinaryOp::BitOr => Some(lhs | rhs),
+                BinaryOp::BitXor => Some(lhs ^ rhs),
+                BinaryOp::ShiftLeft => lhs.checked_shl(rhs.try_into().ok()?),
+                BinaryOp::ShiftRight => lhs.checked_shr(rhs.try_into().ok()?),
+                _ => None,
+            }
+        }
+        ExprKind::Field { expr, field } => match &expr.kind {
+            ExprKind::Ident(ident) if ident.name == enum_name => members
+                .iter()
+                .find(|v| v.name == field.name)
+                .and_then(|v| v.value),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Parses a number literal, in any of gml's bases, as long as it's a whole number.
+fn parse_integer(lexeme: &str) -> Option<i64> {
+    let lexeme = lexeme.replace('_', "");
+    // the lexer takes `0X` and `0B` as well
+    let strip_prefix = |prefix: &str| {
+        lexeme
+            .get(..prefix.len())
+            .filter(|v| v.eq_ignore_ascii_case(prefix))
+            .map(|_| &lexeme[prefix.len()..])
+    };
+
+    if let Some(hex) = lexeme.strip_prefix('$').or_else(|| strip_prefix("0x")) {
+        return i64::from_str_radix(hex, 16).ok();
+    }
+    if let Some(binary) = strip_prefix("0b") {
+        return i64::from_str_radix(binary, 2).ok();
+    }
+
+    let value: f64 = lexeme.parse().ok()?;
+    (value.fract() == 0.0).then_some(value as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(files: &[(&str, ResourceLookupData, &str)]) -> SymbolIndex {
+        let mut index = SymbolIndex::default();
+        for (name, data, txt) in files {
+            let source = ResourceLookup {
+                name: name.to_string(),
+                data: data.clone(),
+            };
+            index.update_file(
+                Path::new(&format!("{}.gml", name)),
+                &source,
+                txt,
+                &SyntaxTree::parse(txt),
+            );
+        }
+        index
+    }
+
+    #[test]
+    fn collects_symbols() {
+        let index = index(&[(
+            "scr_utils",
+            ResourceLookupData::Script,
+            r#"
+/// @description Spawns an enemy
+function scr_spawn(_x, _y = 10) {
+    global.spawned = true;
+    function inner() {}
+}
+scr_lerp = function(a, b, t) { return a + (b - a) * t; }
+function Vector(_x, _y) constructor {}
+#macro Debug:SPEED 5
+enum EState { Idle, Walk }
+globalvar money;
+"#,
+        )]);
+
+        let names: Vec<_> = index.iter().map(|(name, _)| name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "EState",
+                "SPEED",
+                "Vector",
+                "money",
+                "scr_lerp",
+                "scr_spawn",
+                "spawned"
+            ]
+        );
+
+        assert_eq!(
+            index.get("scr_spawn")[0].detail(),
+            "function scr_spawn(_x, _y = 10)"
+        );
+        assert_eq!(
+            index.get("Vector")[0].detail(),
+            "function Vector(_x, _y) constructor"
+        );
+        assert_eq!(index.get("SPEED")[0].detail(), "#macro Debug:SPEED 5");
+        assert_eq!(
+            index.get("EState")[0].detail(),
+            "enum EState { Idle, Walk }"
+        );
+        assert_eq!(index.get("spawned")[0].range.start.line, 3);
+
+        let SymbolKind::Function(func) = &index.get("scr_spawn")[0].kind else {
+            panic!("expected a function");
+        };
+        assert_eq!(func.docs.description, "Spawns an enemy");
+    }
+
+    #[test]
+    fn enum_values() {
+        let index = index(&[(
+            "scr_enums",
+            ResourceLookupData::Script,
+            "enum EFlags { None, A = 1 << 2, B, C = (EFlags.A | EFlags.B) * 2, D = -$10, E = foo(), F }",
+        )]);
+
+        let SymbolKind::Enum(decl) = &index.get("EFlags")[0].kind else {
+            panic!("expected an enum");
+        };
+        let values: Vec<_> = decl.members.iter().map(|v| v.value).collect();
+        assert_eq!(
+            values,
+            vec![Some(0), Some(4), Some(5), Some(10), Some(-16), None, None]
+        );
+        assert_eq!(decl.member("B").unwrap().range.start.column, 32);
+
+        let masks = self::index(&[(
+            "scr_masks",
+            ResourceLookupData::Script,
+            "enum EMasks { A = 0XFF, B, C = 0B11, D = 0x1_0 }",
+        )]);
+        let SymbolKind::Enum(decl) = &masks.get("EMasks")[0].kind else {
+            panic!("expected an enum");
+        };
+        let values: Vec<_> = decl.members.iter().map(|v| v.value).collect();
+        assert_eq!(values, vec![Some(255), Some(256), Some(3), Some(16)]);
+    }
+
+    #[test]
+    fn macro_expansion() {
+        let mac = MacroSymbol {
+            config: None,
+            body: "draw_text(0, 0, \"a\"); \\\r\n    draw_text(0, 16, \"b\");".to_string(),
+        };
+        assert_eq!(
+            mac.expansion(),
+            "draw_text(0, 0, \"a\");\n    draw_text(0, 16, \"b\");"
+        );
+    }
+
+    #[test]
+    fn updates_and_removes_files() {
+        let mut index = index(&[
+            ("scr_a", ResourceLookupData::Script, "function a() {}"),
+            (
+                "obj_player",
+                ResourceLookupData::Object(yy_typings::EventType::Create),
+                "function method() {}\nglobal.player = id;",
+            ),
+        ]);
+        assert!(index.get("method").is_empty());
+        assert_eq!(index.get("player").len(), 1);
+
+        let source = ResourceLookup {
+            name: "scr_a".to_string(),
+            data: ResourceLookupData::Script,
+        };
+        let txt = "function b() {}";
+        index.update_file(
+            Path::new("scr_a.gml"),
+            &source,
+            txt,
+            &SyntaxTree::parse(txt),
+        );
+        assert!(index.get("a").is_empty());
+        assert_eq!(index.get("b").len(), 1);
+
+        index.remove_file(Path::new("obj_player.gml"));
+        assert!(index.get("player").is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// The text of the project's `.yy` files and its `.yyp`, which references and renames look
+/// through. They're read once, when the project is loaded, rather than on every request.
+#[derive(Debug, Default)]
+pub struct YyFiles {
+    texts: BTreeMap<PathBuf, String>,
+}
+
+impl YyFiles {
+    /// Reads in each of `paths`, skipping any which can't be read.
+    pub fn read(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let txt = std::fs::read_to_string(&path).ok()?;
+                Some((path, txt))
+            })
+            .collect()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&str> {
+        self.texts.get(path).map(String::as_str)
+    }
+
+    pub fn get_mut(&mut self, path: &Path) -> Option<&mut String> {
+        self.texts.get_mut(path)
+    }
+
+    /// Moves a file, or every file in a folder, to match a file the client has renamed.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        let moved: Vec<PathBuf> = self
+            .texts
+            .keys()
+            .filter(|v| v.starts_with(from))
+            .cloned()
+            .collect();
+
+        for path in moved {
+            let txt = self.texts.remove(&path).unwrap();
+            let new_path = match path.strip_prefix(from) {
+                Ok(rest) if rest.as_os_str().is_empty() == false => to.join(rest),
+                _ => to.to_path_buf(),
+            };
+            self.texts.insert(new_path, txt);
+        }
+    }
+}
+
+impl FromIterator<(PathBuf, String)> for YyFiles {
+    fn from_iter<T: IntoIterator<Item = (PathBuf, String)>>(iter: T) -> Self {
+        Self {
+            texts: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renames() {
+        let mut files: YyFiles = [
+            ("/project/objects/obj_enemy/obj_enemy.yy", "enemy"),
+            ("/project/objects/obj_enemy_boss/obj_enemy_boss.yy", "boss"),
+        ]
+        .into_iter()
+        .map(|(path, txt)| (PathBuf::from(path), txt.to_string()))
+        .collect();
+
+        files.rename(
+            Path::new("/project/objects/obj_enemy/obj_enemy.yy"),
+            Path::new("/project/objects/obj_enemy/obj_foe.yy"),
+        );
+        files.rename(
+            Path::new("/project/objects/obj_enemy"),
+            Path::new("/project/objects/obj_foe"),
+        );
+
+        assert_eq!(
+            files.get(Path::new("/project/objects/obj_foe/obj_foe.yy")),
+            Some("enemy")
+        );
+        assert_eq!(
+            files.get(Path::new(
+                "/project/objects/obj_enemy_boss/obj_enemy_boss.yy"
+            )),
+            Some("boss")
+        );
+        assert_eq!(
+            files.get(Path::new("/project/objects/obj_enemy/obj_enemy.yy")),
+            None
+        );
+    }
+}